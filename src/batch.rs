@@ -0,0 +1,194 @@
+//! Batch evaluation of an expression over the rows of a CSV file, and
+//! checking a file of expressions parses
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use pratt_calculator::interpreter::interpreter::Interpreter;
+use pratt_calculator::interpreter::parser::PrattParser;
+
+/// Split CSV text into its rows of fields
+///
+/// Fields are separated by commas and may be wrapped in double quotes, in
+/// which case they can contain commas, and `""` is a literal quote.
+/// Blank lines are skipped and whitespace around unquoted fields is
+/// trimmed. Each row is returned along with its line number.
+fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut rows = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields = Vec::new();
+        let mut chars = line.chars().peekable();
+        loop {
+            let mut field = String::new();
+            while chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+                chars.next();
+            }
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            field.push('"');
+                            chars.next();
+                        }
+                        Some('"') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(anyhow!("Unterminated quoted field on line {}", i + 1));
+                        }
+                    }
+                }
+                while chars.peek().is_some_and(|c| *c != ',') {
+                    chars.next();
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| *c != ',') {
+                    field.push(c);
+                }
+                field = field.trim().to_string();
+            }
+            fields.push(field);
+            if chars.next().is_none() {
+                break;
+            }
+        }
+        rows.push((i + 1, fields));
+    }
+    Ok(rows)
+}
+
+/// Evaluate expr once per row of a CSV file, with the columns it uses
+/// bound to the row's values, returning the result for each row
+///
+/// The first row is the header naming the columns. Columns the
+/// expression doesn't use may hold anything, but the ones it does must
+/// be numeric.
+pub(crate) fn map_csv(interpreter: &mut Interpreter, csv: &str, expr: &str) -> Result<Vec<f64>> {
+    let mut rows = parse_csv(csv)?.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Err(anyhow!("The CSV file is empty, expected a header row"));
+    };
+    let parsed = PrattParser::parse_with(expr, interpreter.binding_powers())
+        .context("Failed to parse expression to map")?;
+    // Every variable has to be a column, or already have a value
+    let mut columns = Vec::new();
+    for name in parsed.variables() {
+        match header.iter().position(|column| *column == name) {
+            Some(index) => columns.push((name, index)),
+            None if interpreter.is_defined(&name) => {}
+            None => {
+                return Err(anyhow!(
+                    "Unknown column {name}, the columns are {}",
+                    header.join(", ")
+                ));
+            }
+        }
+    }
+    let mut results = Vec::new();
+    for (line, fields) in rows {
+        let mut bindings = Vec::new();
+        for (name, index) in &columns {
+            let cell = fields
+                .get(*index)
+                .ok_or(anyhow!("Missing value for column {name} on line {line}"))?;
+            let value = cell.parse::<f64>().map_err(|_| {
+                anyhow!("Non-numeric value \"{cell}\" in column {name} on line {line}")
+            })?;
+            bindings.push((name.as_str(), value));
+        }
+        let result = interpreter
+            .interpret_with(expr, &bindings)
+            .with_context(|| format!("Failed to evaluate expression on line {line}"))?;
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Check that each non-blank line of text parses, without evaluating
+/// anything, returning the line number and error of each line which
+/// doesn't
+pub(crate) fn check_lines(interpreter: &Interpreter, text: &str) -> Vec<(usize, anyhow::Error)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| interpreter.check(line).err().map(|err| (i + 1, err)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test_batch {
+    use super::*;
+    use pratt_calculator::interpreter::parser::AssignmentStyle;
+
+    #[test]
+    fn test_parse_csv() -> Result<()> {
+        let rows = parse_csv("a, b,\"c, d\"\n\n1,\"say \"\"hi\"\"\" , 3\n")?;
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    1,
+                    vec!["a".to_string(), "b".to_string(), "c, d".to_string()]
+                ),
+                (
+                    3,
+                    vec!["1".to_string(), "say \"hi\"".to_string(), "3".to_string()]
+                ),
+            ]
+        );
+        assert!(parse_csv("a,\"b\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_csv() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("rate = 2")?;
+        let csv = "item,price,qty\nnut,1.5,4\nbolt,0.25,10\n";
+        assert_eq!(
+            map_csv(&mut test_interpreter, csv, "price * qty * rate")?,
+            vec![12f64, 5f64]
+        );
+        // Columns are only bound while each row is evaluated
+        assert!(!test_interpreter.is_defined("price"));
+
+        let err = map_csv(&mut test_interpreter, csv, "price * weight").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown column weight, the columns are item, price, qty"
+        );
+        let err = map_csv(&mut test_interpreter, csv, "item + 1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Non-numeric value \"nut\" in column item on line 2"
+        );
+        let err = map_csv(&mut test_interpreter, "a,b\n1\n", "a + b").unwrap_err();
+        assert_eq!(err.to_string(), "Missing value for column b on line 2");
+
+        // The expression is parsed with the interpreter's assignment style
+        test_interpreter.set_assignment_style(AssignmentStyle::ColonEquals);
+        assert_eq!(
+            map_csv(&mut test_interpreter, csv, "(rate := 3) * price")?,
+            vec![4.5f64, 0.75f64]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_lines() {
+        let test_interpreter = Interpreter::new();
+        let failures = check_lines(&test_interpreter, "1 + 2\n\n3 +\nx = (4\ny = 5; 1 / y\n");
+        assert_eq!(
+            failures
+                .iter()
+                .map(|(line, _)| *line)
+                .collect::<Vec<usize>>(),
+            vec![3, 4]
+        );
+    }
+}
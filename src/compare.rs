@@ -0,0 +1,253 @@
+//! Numerical comparison of two expressions
+// Standard Library Uses
+use std::fmt;
+
+// External Uses
+use anyhow::{Context, Result};
+
+// Local Uses
+use pratt_calculator::interpreter::interpreter::Interpreter;
+use pratt_calculator::interpreter::parser::PrattParser;
+
+/// Values tried for each free variable when comparing expressions
+pub(crate) const DEFAULT_GRID: &[f64] =
+    &[-100.0, -10.0, -2.5, -1.0, 0.0, 0.5, 1.0, 3.0, 10.0, 1000.0];
+
+/// Default largest difference for two values to count as equal
+pub(crate) const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+/// Values of the free variables at one sample point
+type Sample = Vec<(String, f64)>;
+
+/// Result of comparing two expressions over a set of sample points
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Comparison {
+    /// Number of sample points the expressions were compared at
+    pub(crate) samples: usize,
+    /// Largest absolute difference and where it occurred
+    pub(crate) max_absolute: Option<(f64, Sample)>,
+    /// Largest relative difference and where it occurred
+    pub(crate) max_relative: Option<(f64, Sample)>,
+    /// Sample points where either expression was NaN or infinite
+    pub(crate) non_finite: Vec<Sample>,
+}
+
+/// Format the variable values of a sample point
+fn format_sample(sample: &Sample) -> String {
+    if sample.is_empty() {
+        return "current values".to_string();
+    }
+    sample
+        .iter()
+        .map(|(name, value)| format!("{name} = {value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "compared at {} points", self.samples)?;
+        if let Some((diff, sample)) = &self.max_absolute {
+            write!(
+                f,
+                "\nmax absolute difference: {diff} at {}",
+                format_sample(sample)
+            )?;
+        }
+        if let Some((diff, sample)) = &self.max_relative {
+            write!(
+                f,
+                "\nmax relative difference: {diff} at {}",
+                format_sample(sample)
+            )?;
+        }
+        if !self.non_finite.is_empty() {
+            let samples = self
+                .non_finite
+                .iter()
+                .map(format_sample)
+                .collect::<Vec<_>>();
+            write!(f, "\nnon-finite results at: {}", samples.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two expressions, evaluating them against the current
+/// environment, with every free variable swept over the values in grid
+pub(crate) fn compare(
+    interpreter: &mut Interpreter,
+    old: &str,
+    new: &str,
+    grid: &[f64],
+) -> Result<Comparison> {
+    let mut free_variables = PrattParser::parse_with(old, interpreter.binding_powers())
+        .context("Failed to parse first expression")?
+        .variables();
+    free_variables.append(
+        &mut PrattParser::parse_with(new, interpreter.binding_powers())
+            .context("Failed to parse second expression")?
+            .variables(),
+    );
+    free_variables.retain(|name| !interpreter.is_defined(name));
+
+    // Every combination of grid values for the free variables
+    let mut samples: Vec<Sample> = vec![Vec::new()];
+    for name in free_variables {
+        samples = samples
+            .iter()
+            .flat_map(|sample| {
+                grid.iter().map(|&value| {
+                    let mut sample = sample.clone();
+                    sample.push((name.clone(), value));
+                    sample
+                })
+            })
+            .collect();
+    }
+
+    let mut comparison = Comparison::default();
+    for sample in samples {
+        let bindings = sample
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect::<Vec<_>>();
+        let old_value = interpreter
+            .interpret_with(old, &bindings)
+            .with_context(|| format!("Failed to evaluate {old} at {}", format_sample(&sample)))?;
+        let new_value = interpreter
+            .interpret_with(new, &bindings)
+            .with_context(|| format!("Failed to evaluate {new} at {}", format_sample(&sample)))?;
+        comparison.samples += 1;
+        if !old_value.is_finite() || !new_value.is_finite() {
+            comparison.non_finite.push(sample);
+            continue;
+        }
+        let absolute = (old_value - new_value).abs();
+        let scale = old_value.abs().max(new_value.abs());
+        let relative = if scale == 0f64 {
+            0f64
+        } else {
+            absolute / scale
+        };
+        if comparison
+            .max_absolute
+            .as_ref()
+            .is_none_or(|(max, _)| absolute > *max)
+        {
+            comparison.max_absolute = Some((absolute, sample.clone()));
+        }
+        if comparison
+            .max_relative
+            .as_ref()
+            .is_none_or(|(max, _)| relative > *max)
+        {
+            comparison.max_relative = Some((relative, sample));
+        }
+    }
+    Ok(comparison)
+}
+
+/// Whether two values differ by no more than tolerance, infinities are
+/// only equivalent to themselves and NaN is never equivalent to anything
+pub(crate) fn equivalent(a: f64, b: f64, tolerance: f64) -> bool {
+    a == b || (a.is_finite() && b.is_finite() && (a - b).abs() <= tolerance)
+}
+
+/// Split the input of `:compare` into its two expressions, at the `;`
+/// separating them
+///
+/// Unlike `|`, which is bitwise or, `;` can't be part of an expression,
+/// so there is no ambiguity in where the first expression ends.
+pub(crate) fn split_expressions(input: &str) -> Option<(&str, &str)> {
+    input
+        .split_once(';')
+        .map(|(old, new)| (old.trim(), new.trim()))
+}
+
+#[cfg(test)]
+mod test_compare {
+    use super::*;
+    use pratt_calculator::interpreter::parser::AssignmentStyle;
+
+    #[test]
+    fn test_split_expressions() {
+        assert_eq!(split_expressions("x + 1 ; 1 + x"), Some(("x + 1", "1 + x")));
+        assert_eq!(split_expressions("x | 1;x | 1"), Some(("x | 1", "x | 1")));
+        assert_eq!(split_expressions("x | 1 | x"), None);
+    }
+
+    #[test]
+    fn test_equivalent_expressions() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let comparison = compare(
+            &mut test_interpreter,
+            "x*(x+1)/2",
+            "(x^2 + x)/2",
+            &[0.0, 1.0, 2.0, 3.0, 10.0],
+        )?;
+        assert_eq!(comparison.samples, 5);
+        assert_eq!(comparison.max_absolute.map(|(diff, _)| diff), Some(0f64));
+        assert_eq!(comparison.max_relative.map(|(diff, _)| diff), Some(0f64));
+        assert!(comparison.non_finite.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_different_expressions() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("a = 2")?;
+        let comparison = compare(&mut test_interpreter, "x^a", "a*x", &[-1.0, 0.0, 2.0, 5.0])?;
+        assert_eq!(
+            comparison.max_absolute,
+            Some((15f64, vec![("x".to_string(), 5f64)]))
+        );
+        assert_eq!(
+            comparison.max_relative,
+            Some((1.5f64, vec![("x".to_string(), -1f64)]))
+        );
+        assert_eq!(
+            comparison.to_string(),
+            "compared at 4 points\nmax absolute difference: 15 at x = 5\n\
+            max relative difference: 1.5 at x = -1"
+        );
+        // x is not left bound afterwards
+        assert!(!test_interpreter.is_defined("x"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_assignment_style() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.set_assignment_style(AssignmentStyle::ColonEquals);
+        test_interpreter.interpret("a := 2")?;
+        // Both expressions are parsed with the interpreter's := assignment
+        let comparison = compare(&mut test_interpreter, "x * (a := 2)", "2 * x", &[0.0, 1.0])?;
+        assert_eq!(comparison.samples, 2);
+        assert_eq!(comparison.max_absolute.map(|(diff, _)| diff), Some(0f64));
+        assert!(!test_interpreter.is_defined("x"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_finite_results() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let comparison = compare(&mut test_interpreter, "1/x", "1/x", &[0.0, 1.0])?;
+        assert_eq!(comparison.non_finite, vec![vec![("x".to_string(), 0f64)]]);
+        assert_eq!(
+            comparison.to_string(),
+            "compared at 2 points\nmax absolute difference: 0 at x = 1\n\
+            max relative difference: 0 at x = 1\nnon-finite results at: x = 0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_equivalent_values() {
+        assert!(equivalent(0.1 + 0.2, 0.3, DEFAULT_TOLERANCE));
+        assert!(!equivalent(0.1 + 0.2, 0.3, 0f64));
+        assert!(equivalent(f64::INFINITY, f64::INFINITY, 0f64));
+        assert!(!equivalent(f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY));
+        assert!(!equivalent(f64::NAN, f64::NAN, f64::INFINITY));
+    }
+}
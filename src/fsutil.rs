@@ -0,0 +1,141 @@
+//! File writing shared by everything which saves to disk
+// Standard Library Uses
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+/// Replace the contents of the file at path, creating it (and any missing
+/// parent directories) if needed
+///
+/// The contents are written to a temporary file next to the target, synced
+/// to disk, then renamed over the target, so a crash part way through
+/// leaves either the old file or the new one, never a truncated mix.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic_with(path, |file| file.write_all(contents))
+}
+
+/// Atomically replace the file at path with whatever write produces,
+/// leaving the original untouched if write fails
+fn write_atomic_with<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let Some(name) = path.file_name() else {
+        return Err(anyhow!(
+            "Can't write to {}, it isn't a file",
+            path.display()
+        ));
+    };
+    let parent = path.parent().unwrap_or(Path::new(""));
+    if !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    let temporary = parent.join(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+    let res = write_temporary(&temporary, write).and_then(|()| {
+        fs::rename(&temporary, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    });
+    if res.is_err() {
+        // The temporary file is useless once writing it has failed
+        let _ = fs::remove_file(&temporary);
+    }
+    res.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write a new temporary file and sync it to disk
+fn write_temporary<F>(temporary: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let mut file = File::create(temporary)
+        .with_context(|| format!("Failed to create {}", temporary.display()))?;
+    write(&mut file)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_fsutil {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    /// A fresh, empty directory for a test to write into
+    fn test_directory(name: &str) -> Result<PathBuf> {
+        let directory = env::temp_dir().join(format!("pratt_calculator_{name}_{}", process::id()));
+        if directory.exists() {
+            fs::remove_dir_all(&directory)?;
+        }
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let directory = test_directory("write_atomic")?;
+        let path = directory.join("nested").join("session.txt");
+        write_atomic(&path, b"x = 1\n")?;
+        assert_eq!(fs::read_to_string(&path)?, "x = 1\n");
+        write_atomic(&path, b"x = 2\n")?;
+        assert_eq!(fs::read_to_string(&path)?, "x = 2\n");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(path.parent().unwrap())?.count(), 1);
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_write_keeps_original() -> Result<()> {
+        let directory = test_directory("failed_write")?;
+        let path = directory.join("session.txt");
+        write_atomic(&path, b"original\n")?;
+        let err = write_atomic_with(&path, |file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::other("disk full"))
+        })
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            format!("Failed to write {}: disk full", path.display())
+        );
+        assert_eq!(fs::read_to_string(&path)?, "original\n");
+        assert_eq!(fs::read_dir(&directory)?.count(), 1);
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_directory() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        let directory = test_directory("read_only")?;
+        let path = directory.join("session.txt");
+        write_atomic(&path, b"original\n")?;
+        fs::set_permissions(&directory, fs::Permissions::from_mode(0o555))?;
+        // Privileged users, such as root, can write to read-only
+        // directories anyway, so there is no failure to check
+        let enforced = File::create(directory.join("probe")).is_err();
+        let res = write_atomic(&path, b"replaced\n");
+        fs::set_permissions(&directory, fs::Permissions::from_mode(0o755))?;
+        if enforced {
+            let message = format!("{:#}", res.unwrap_err());
+            assert!(
+                message.starts_with(&format!(
+                    "Failed to write {}: Failed to create",
+                    path.display()
+                )),
+                "{message}"
+            );
+            assert!(message.contains("Permission denied"), "{message}");
+            assert_eq!(fs::read_to_string(&path)?, "original\n");
+            assert_eq!(fs::read_dir(&directory)?.count(), 1);
+        }
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -1,3 +1,12 @@
+pub mod analysis;
+pub mod anonymize;
+pub mod builder;
+pub mod debugger;
+pub mod format;
+pub mod grammar;
+#[allow(clippy::module_inception)]
 pub mod interpreter;
 pub mod lexer;
 pub mod parser;
+pub mod rpn;
+pub mod spreadsheet;
@@ -0,0 +1,299 @@
+//! Static analysis passes over parsed S-expressions
+// Standard Library Uses
+use std::collections::BTreeSet;
+use std::fmt;
+
+// External Uses
+
+// Local Uses
+use super::lexer::Operator;
+use super::parser::{SExpr, SExprAtom};
+
+/// Relative cost of a function call such as `sqrt` or `sin`, compared to
+/// one arithmetic operation
+const CALL_COST: u64 = 10;
+
+/// Rough estimate of the cost of evaluating an expression, broken down
+/// by the kind of operation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CostReport {
+    /// Number of numbers and variables
+    pub atoms: usize,
+    /// Number of cheap operations (arithmetic such as + and ^, along with
+    /// comparisons, logical and bitwise operators)
+    pub arithmetic: usize,
+    /// Number of function calls, such as `sqrt` or `sin`
+    pub calls: usize,
+    /// Number of assignments
+    pub assignments: usize,
+    /// Number of factorials
+    pub factorials: usize,
+    /// Total loop iterations of the factorials with literal operands
+    pub factorial_iterations: u64,
+    /// Number of factorials whose iterations depend on a variable or
+    /// other expression, so can't be known ahead of time
+    pub unbounded_factorials: usize,
+}
+
+impl CostReport {
+    /// Overall relative score, one per operation or loop iteration and
+    /// more for each function call, not counting any unbounded loops
+    pub fn score(&self) -> u64 {
+        (self.arithmetic + self.assignments) as u64
+            + self.calls as u64 * CALL_COST
+            + self.factorial_iterations
+    }
+
+    /// Whether the cost depends on loops with unknown iteration counts
+    pub fn is_bounded(&self) -> bool {
+        self.unbounded_factorials == 0
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "atoms: {}", self.atoms)?;
+        writeln!(f, "arithmetic: {}", self.arithmetic)?;
+        writeln!(f, "calls: {}", self.calls)?;
+        writeln!(f, "assignments: {}", self.assignments)?;
+        write!(
+            f,
+            "factorials: {} ({} iterations",
+            self.factorials, self.factorial_iterations
+        )?;
+        if self.is_bounded() {
+            writeln!(f, ")")?;
+            write!(f, "relative score: {}", self.score())
+        } else {
+            writeln!(
+                f,
+                ", {} unbounded/depends on variable)",
+                self.unbounded_factorials
+            )?;
+            write!(f, "relative score: {} + unbounded", self.score())
+        }
+    }
+}
+
+impl SExpr {
+    /// Estimate the cost of evaluating this expression
+    pub fn cost_estimate(&self) -> CostReport {
+        let mut report = CostReport::default();
+        self.add_cost(&mut report);
+        report
+    }
+
+    /// Warnings for divisions of whole number literals with a fractional
+    /// result, such as `7 / 2`, where integer division was probably meant
+    ///
+    /// Only a division making up the whole expression (or the value it
+    /// assigns), or taking the factorial of its result, is flagged, since
+    /// inside larger expressions fractions are usually intended.
+    pub fn integer_division_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let top = match self {
+            SExpr::Cons(SExprAtom::Op(op), operands) if op.is_assignment() => operands.last(),
+            _ => Some(self),
+        };
+        if let Some(warning) = top.and_then(SExpr::integer_division_warning) {
+            warnings.push(warning);
+        }
+        self.add_factorial_division_warnings(&mut warnings);
+        warnings
+    }
+
+    /// The warning for this expression if it divides two whole number
+    /// literals with a fractional result
+    fn integer_division_warning(&self) -> Option<String> {
+        let SExpr::Cons(SExprAtom::Op(Operator::Slash), operands) = self else {
+            return None;
+        };
+        let [
+            SExpr::Atom(SExprAtom::Integer(lhs)),
+            SExpr::Atom(SExprAtom::Integer(rhs)),
+        ] = operands[..]
+        else {
+            return None;
+        };
+        if rhs == 0 || lhs % rhs == 0 {
+            return None;
+        }
+        Some(format!(
+            "{lhs} / {rhs} is {}, use {lhs} // {rhs} for integer division or write {lhs}.0 / {rhs} if a fraction is intended",
+            lhs as f64 / rhs as f64
+        ))
+    }
+
+    /// Add warnings for the divisions of whole number literals whose
+    /// factorial is taken anywhere in this expression
+    fn add_factorial_division_warnings(&self, warnings: &mut Vec<String>) {
+        match self {
+            SExpr::Atom(_) => {}
+            SExpr::Cons(op, operands) => {
+                if matches!(op, SExprAtom::Op(Operator::Bang))
+                    && let Some(warning) =
+                        operands.first().and_then(SExpr::integer_division_warning)
+                {
+                    warnings.push(warning);
+                }
+                for operand in operands {
+                    operand.add_factorial_division_warnings(warnings);
+                }
+            }
+            SExpr::Where(body, bindings) => {
+                for (_, value) in bindings {
+                    value.add_factorial_division_warnings(warnings);
+                }
+                body.add_factorial_division_warnings(warnings);
+            }
+        }
+    }
+
+    /// Get the names of all the variables used in this expression
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut variables = BTreeSet::new();
+        self.add_variables(&mut variables);
+        variables
+    }
+
+    /// Add the variables used in this expression to a set
+    fn add_variables(&self, variables: &mut BTreeSet<String>) {
+        match self {
+            SExpr::Atom(SExprAtom::Variable(varname)) => {
+                variables.insert(varname.clone());
+            }
+            SExpr::Atom(_) => {}
+            SExpr::Cons(_, operands) => {
+                for operand in operands {
+                    operand.add_variables(variables);
+                }
+            }
+            // Variables bound by the where clause are local, so only
+            // count uses from outside of it
+            SExpr::Where(body, bindings) => {
+                let mut local = BTreeSet::new();
+                for (name, value) in bindings {
+                    variables.extend(value.variables().difference(&local).cloned());
+                    local.insert(name.clone());
+                }
+                variables.extend(body.variables().difference(&local).cloned());
+            }
+        }
+    }
+
+    /// Add the cost of this expression and its operands to a report
+    fn add_cost(&self, report: &mut CostReport) {
+        match self {
+            SExpr::Atom(_) => report.atoms += 1,
+            SExpr::Cons(op, operands) => {
+                match op {
+                    SExprAtom::Op(op) if op.is_assignment() => report.assignments += 1,
+                    SExprAtom::Variable(_) => report.calls += 1,
+                    SExprAtom::Op(Operator::Bang) => {
+                        report.factorials += 1;
+                        match operands.first() {
+                            Some(SExpr::Atom(SExprAtom::Number(n))) => {
+                                report.factorial_iterations += n.abs().trunc() as u64
+                            }
+                            Some(SExpr::Atom(SExprAtom::Integer(n))) => {
+                                report.factorial_iterations += n.unsigned_abs()
+                            }
+                            _ => report.unbounded_factorials += 1,
+                        }
+                    }
+                    _ => report.arithmetic += 1,
+                }
+                for operand in operands {
+                    operand.add_cost(report);
+                }
+            }
+            SExpr::Where(body, bindings) => {
+                for (_, value) in bindings {
+                    report.assignments += 1;
+                    value.add_cost(report);
+                }
+                body.add_cost(report);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_analysis {
+    use super::*;
+    use crate::interpreter::parser::PrattParser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_cost_estimate() -> Result<()> {
+        let report =
+            PrattParser::parse("a = 2 * 5! + x! - 3^2 + sqrt(x) * max(1, 2)")?.cost_estimate();
+        assert_eq!(
+            report,
+            CostReport {
+                atoms: 9,
+                arithmetic: 6,
+                calls: 2,
+                assignments: 1,
+                factorials: 2,
+                factorial_iterations: 5,
+                unbounded_factorials: 1,
+            }
+        );
+        assert_eq!(report.score(), 32);
+        assert!(!report.is_bounded());
+        assert_eq!(
+            report.to_string(),
+            "atoms: 9\narithmetic: 6\ncalls: 2\nassignments: 1\n\
+            factorials: 2 (5 iterations, 1 unbounded/depends on variable)\n\
+            relative score: 32 + unbounded"
+        );
+
+        let report = PrattParser::parse("-(3 + 4)")?.cost_estimate();
+        assert_eq!(report.score(), 2);
+        assert!(report.is_bounded());
+        Ok(())
+    }
+
+    #[test]
+    fn test_integer_division_warnings() -> Result<()> {
+        let warnings = |input| -> Result<Vec<String>> {
+            Ok(PrattParser::parse(input)?.integer_division_warnings())
+        };
+        assert_eq!(
+            warnings("7 / 2")?,
+            vec![
+                "7 / 2 is 3.5, use 7 // 2 for integer division or write 7.0 / 2 if a fraction is intended"
+                    .to_string()
+            ]
+        );
+        assert_eq!(warnings("half = 1 / 2")?.len(), 1);
+        assert_eq!(warnings("(9 / 2)! + 1")?.len(), 1);
+        // Whole results, decimals, variables and division by zero are fine
+        assert!(warnings("8 / 2")?.is_empty());
+        assert!(warnings("7.0 / 2")?.is_empty());
+        assert!(warnings("x / 2")?.is_empty());
+        assert!(warnings("1 / 0")?.is_empty());
+        assert!(warnings("7 // 2")?.is_empty());
+        // as are fractions inside larger expressions
+        assert!(warnings("x * (1 / 2)")?.is_empty());
+        assert!(warnings("sqrt(3 / 4)")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_variables() -> Result<()> {
+        let variables = PrattParser::parse("y = x * (x + z) - 2")?.variables();
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            vec!["x".to_string(), "y".to_string(), "z".to_string()]
+        );
+        assert!(PrattParser::parse("3 + 4!")?.variables().is_empty());
+        let variables = PrattParser::parse("u * v + w where u = x + 1, v = u * w")?.variables();
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            vec!["w".to_string(), "x".to_string()]
+        );
+        Ok(())
+    }
+}
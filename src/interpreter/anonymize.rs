@@ -0,0 +1,134 @@
+//! Anonymization of expressions, for sharing them without revealing
+//! what they compute
+// Standard Library Uses
+use std::collections::HashMap;
+
+// External Uses
+
+// Local Uses
+use super::interpreter::{ANSWER, MEMORY_RECALL};
+use super::parser::{SExpr, SExprAtom};
+
+/// An anonymized expression along with how its variables were renamed
+#[derive(Clone, Debug)]
+pub struct Anonymized {
+    /// The expression with its variables renamed
+    pub expr: SExpr,
+    /// The original name of each replacement, in order of first use
+    pub mapping: Vec<(String, String)>,
+}
+
+impl SExpr {
+    /// Rename the variables of this expression to `v1`, `v2`, … in order
+    /// of first use, the same name always getting the same replacement
+    ///
+    /// Unless keep_numbers is set, number literals are also rounded to one
+    /// significant figure, so `1234.5` becomes `1000`. The built in names
+    /// `MR` and `ans` are kept as they are.
+    pub fn anonymize(&self, keep_numbers: bool) -> Anonymized {
+        let mut renames = HashMap::new();
+        let mut mapping = Vec::new();
+        let mut rename = |name: &str| -> String {
+            if name == MEMORY_RECALL || name == ANSWER {
+                return name.to_string();
+            }
+            renames
+                .entry(name.to_string())
+                .or_insert_with(|| {
+                    let replacement = format!("v{}", mapping.len() + 1);
+                    mapping.push((replacement.clone(), name.to_string()));
+                    replacement
+                })
+                .clone()
+        };
+        let expr = self.map_atoms(&mut |atom| match atom {
+            SExprAtom::Variable(name) => SExprAtom::Variable(rename(name)),
+            SExprAtom::Number(n) if !keep_numbers => SExprAtom::Number(bucket_number(*n)),
+            SExprAtom::Integer(n) if !keep_numbers => SExprAtom::Number(bucket_number(*n as f64)),
+            atom => atom.clone(),
+        });
+        Anonymized { expr, mapping }
+    }
+
+    /// Rebuild the expression with every atom (and variable bound by a
+    /// where clause) replaced, visiting them left to right
+    fn map_atoms(&self, f: &mut impl FnMut(&SExprAtom) -> SExprAtom) -> SExpr {
+        match self {
+            SExpr::Atom(atom) => SExpr::Atom(f(atom)),
+            SExpr::Cons(op, operands) => SExpr::Cons(
+                op.clone(),
+                operands
+                    .iter()
+                    .map(|operand| operand.map_atoms(f))
+                    .collect(),
+            ),
+            SExpr::Where(body, bindings) => {
+                // Bindings are evaluated before the body, so visit them first
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value.map_atoms(f);
+                        match f(&SExprAtom::Variable(name.clone())) {
+                            SExprAtom::Variable(name) => (name, value),
+                            _ => (name.clone(), value),
+                        }
+                    })
+                    .collect();
+                SExpr::Where(Box::new(body.map_atoms(f)), bindings)
+            }
+        }
+    }
+}
+
+/// Round a number to one significant figure
+fn bucket_number(n: f64) -> f64 {
+    if n == 0f64 || !n.is_finite() {
+        return n;
+    }
+    // Going through scientific notation avoids float error creeping in,
+    // 0.3 stays 0.3 rather than becoming 0.30000000000000004
+    format!("{n:.0e}").parse().unwrap_or(n)
+}
+
+#[cfg(test)]
+mod test_anonymize {
+    use crate::interpreter::parser::PrattParser;
+    use anyhow::Result;
+
+    #[test]
+    fn test_consistent_renaming() -> Result<()> {
+        let expr = PrattParser::parse("profit = revenue * margin - costs * margin + ans")?;
+        let anonymized = expr.anonymize(true);
+        assert_eq!(
+            anonymized.expr.to_infix(),
+            "v1 = (((v2 * v3) - (v4 * v3)) + ans)"
+        );
+        assert_eq!(
+            anonymized.mapping,
+            vec![
+                ("v1".to_string(), "profit".to_string()),
+                ("v2".to_string(), "revenue".to_string()),
+                ("v3".to_string(), "margin".to_string()),
+                ("v4".to_string(), "costs".to_string()),
+            ]
+        );
+        // The anonymized form parses back to the same tree
+        let reparsed = PrattParser::parse(&anonymized.expr.to_infix())?;
+        assert_eq!(reparsed.to_string(), anonymized.expr.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_number_bucketing() -> Result<()> {
+        let expr = PrattParser::parse("rate * 1234.5 + 0.31 - 7 + 0 where rate = 0.0456")?;
+        assert_eq!(
+            expr.anonymize(false).expr.to_infix(),
+            "((((v1 * 1000) + 0.3) - 7) + 0) where v1 = 0.05"
+        );
+        assert_eq!(
+            expr.anonymize(true).expr.to_infix(),
+            "((((v1 * 1234.5) + 0.31) - 7) + 0) where v1 = 0.0456"
+        );
+        Ok(())
+    }
+}
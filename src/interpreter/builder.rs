@@ -0,0 +1,181 @@
+//! Building a configured interpreter in one expression
+//!
+//! Every setting of the [`Interpreter`] has a chainable method on the
+//! [`InterpreterBuilder`], and a [`Preset`] sets several at once for a
+//! typical use. Settings given after a preset override it.
+//!
+//! ```
+//! use pratt_calculator::interpreter::builder::Preset;
+//! use pratt_calculator::interpreter::interpreter::{DivisionMode, Interpreter};
+//!
+//! let mut interpreter = Interpreter::builder()
+//!     .preset(Preset::Interactive)
+//!     .division_mode(DivisionMode::Integer)
+//!     .build();
+//! assert_eq!(interpreter.interpret("7 / 2").unwrap(), 3.0);
+//! assert!(interpreter.interpret("x = 0 / 0").is_err());
+//! ```
+// Standard Library Uses
+
+// External Uses
+use anyhow::Result;
+
+// Local Uses
+use super::format::{NumberFormat, SpecialValues};
+use super::interpreter::{DivisionMode, Interpreter, RoundingMode};
+use super::lexer::Operator;
+use super::parser::{AssignmentStyle, BindingPowers};
+
+/// Named groups of settings for common uses of the interpreter
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Preset {
+    /// For people typing expressions, as in the REPL, where NaN and
+    /// infinities are kept out of variables so a slip can't spoil later
+    /// results
+    Interactive,
+    /// For programs embedding the calculator, where non-finite values are
+    /// stored as they are and left for the host to check
+    Embedded,
+}
+
+/// Builder of an [`Interpreter`], made by [`Interpreter::builder`]
+#[derive(Clone, Debug, Default)]
+pub struct InterpreterBuilder {
+    division_mode: DivisionMode,
+    rounding: RoundingMode,
+    quarantine_nonfinite: bool,
+    number_format: NumberFormat,
+    special_values: SpecialValues,
+    /// Binding powers of the infix operators, along with the assignment
+    /// style
+    binding_powers: BindingPowers,
+}
+
+impl Interpreter {
+    /// Start building an interpreter, with every setting at its default
+    /// as in [`Interpreter::new`]
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+}
+
+impl InterpreterBuilder {
+    /// Apply the settings of a preset
+    pub fn preset(self, preset: Preset) -> Self {
+        match preset {
+            Preset::Interactive => self.quarantine_nonfinite(true),
+            Preset::Embedded => self.quarantine_nonfinite(false),
+        }
+    }
+
+    /// Set how `/` treats whole number operands
+    pub fn division_mode(mut self, mode: DivisionMode) -> Self {
+        self.division_mode = mode;
+        self
+    }
+
+    /// Set how `round` breaks ties
+    pub fn rounding(mut self, mode: RoundingMode) -> Self {
+        self.rounding = mode;
+        self
+    }
+
+    /// Set whether NaN and infinite values are refused when storing
+    /// variables
+    pub fn quarantine_nonfinite(mut self, quarantine: bool) -> Self {
+        self.quarantine_nonfinite = quarantine;
+        self
+    }
+
+    /// Set how results are written out
+    pub fn number_format(mut self, format: NumberFormat) -> Self {
+        self.number_format = format;
+        self
+    }
+
+    /// Set how NaN and infinite results are written out
+    pub fn special_values(mut self, special_values: SpecialValues) -> Self {
+        self.special_values = special_values;
+        self
+    }
+
+    /// Set which operator assigns to a variable
+    pub fn assignment_style(mut self, style: AssignmentStyle) -> Self {
+        self.binding_powers.set_assignment_style(style);
+        self
+    }
+
+    /// Set the left and right binding powers of an infix operator,
+    /// failing if op isn't an infix operator
+    pub fn binding_power(mut self, op: Operator, left: u8, right: u8) -> Result<Self> {
+        self.binding_powers.set_infix(op, left, right)?;
+        Ok(self)
+    }
+
+    /// Build the interpreter
+    pub fn build(self) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_division_mode(self.division_mode);
+        interpreter.set_rounding(self.rounding);
+        interpreter.set_quarantine_nonfinite(self.quarantine_nonfinite);
+        interpreter.set_number_format(self.number_format);
+        interpreter.set_special_values(self.special_values);
+        interpreter.set_binding_powers(self.binding_powers);
+        interpreter
+    }
+}
+
+#[cfg(test)]
+mod test_builder {
+    use super::*;
+
+    #[test]
+    fn test_presets() -> Result<()> {
+        let mut interactive = Interpreter::builder().preset(Preset::Interactive).build();
+        assert!(interactive.interpret("a = 0/0").is_err());
+        assert!(interactive.quarantine_nonfinite());
+
+        let mut embedded = Interpreter::builder().preset(Preset::Embedded).build();
+        assert!(embedded.interpret("a = 0/0")?.is_nan());
+        assert!(!embedded.quarantine_nonfinite());
+
+        // Settings after a preset override it, as do later presets
+        let overridden = Interpreter::builder()
+            .preset(Preset::Interactive)
+            .quarantine_nonfinite(false)
+            .build();
+        assert!(!overridden.quarantine_nonfinite());
+        let reset = Interpreter::builder()
+            .preset(Preset::Interactive)
+            .preset(Preset::Embedded)
+            .build();
+        assert!(!reset.quarantine_nonfinite());
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings() -> Result<()> {
+        let mut interpreter = Interpreter::builder()
+            .division_mode(DivisionMode::Integer)
+            .rounding(RoundingMode::HalfEven)
+            .number_format(NumberFormat::Humane)
+            .special_values(SpecialValues::Readable)
+            .assignment_style(AssignmentStyle::ColonEquals)
+            .binding_power(Operator::Plus, 23, 24)?
+            .build();
+        assert_eq!(interpreter.interpret("7 / 2")?, 3f64);
+        assert_eq!(interpreter.interpret("round(2.5)")?, 2f64);
+        assert_eq!(interpreter.interpret_full("3.2e6")?.display, "3.2 million");
+        assert_eq!(interpreter.interpret_full("1.0/0")?.display, "∞");
+        assert_eq!(interpreter.interpret("x := 2")?, 2f64);
+        assert_eq!(interpreter.interpret("2 * 3 + 4")?, 14f64);
+
+        assert!(
+            Interpreter::builder()
+                .binding_power(Operator::Bang, 1, 2)
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Step by step evaluation of expressions
+//!
+//! A [`DebugSession`] evaluates an expression one node at a time, always
+//! reducing the next node in the order the interpreter would evaluate it
+//! (operands left to right, then the operation) to a number. Conditionals
+//! and logical operators short-circuit, so only their first operand is
+//! reduced before the whole node. Assignments change the interpreter's
+//! environment as soon as the assignment node itself is stepped past, so
+//! dropping a session part way through keeps the assignments which have
+//! already been stepped over, and none of the later ones.
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result};
+
+// Local Uses
+use super::interpreter::Interpreter;
+use super::lexer::Operator;
+use super::parser::{PrattParser, SExpr, SExprAtom};
+
+/// Information about a single evaluation step
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepInfo {
+    /// The node which was reduced this step, in infix form
+    pub current_node_infix: String,
+    /// The whole expression after this step, in infix form
+    pub partial_tree_infix: String,
+    /// Variables assigned during this step, along with their new values
+    pub env_changes: Vec<(String, f64)>,
+}
+
+/// An in progress step by step evaluation of an expression
+pub struct DebugSession<'a> {
+    /// The interpreter evaluating each step
+    interpreter: &'a mut Interpreter,
+    /// The partially evaluated expression
+    tree: SExpr,
+}
+
+impl Interpreter {
+    /// Start evaluating an expression step by step
+    pub fn debug(&mut self, input: &str) -> Result<DebugSession<'_>> {
+        let tree = PrattParser::parse_with(input, self.binding_powers())
+            .context("Trying to parse input into S-expression for debugging")?;
+        Ok(DebugSession {
+            interpreter: self,
+            tree,
+        })
+    }
+}
+
+impl DebugSession<'_> {
+    /// Evaluate the next node, returning None once evaluation is finished
+    pub fn step(&mut self) -> Result<Option<StepInfo>> {
+        let mut tree = self.tree.clone();
+        let step = self.reduce_next(&mut tree)?;
+        self.tree = tree;
+        Ok(step.map(|step| StepInfo {
+            partial_tree_infix: self.tree.to_infix(),
+            ..step
+        }))
+    }
+
+    /// Finish evaluating the expression, returning its value
+    pub fn run_to_completion(mut self) -> Result<f64> {
+        while self.step()?.is_some() {}
+        self.interpreter.interpret_sexpr(self.tree)
+    }
+
+    /// Reduce the next node in evaluation order within expr to a number,
+    /// leaving the partial tree for the caller to fill in
+    fn reduce_next(&mut self, expr: &mut SExpr) -> Result<Option<StepInfo>> {
+        let assigned = match expr {
+            SExpr::Atom(SExprAtom::Number(_) | SExprAtom::Integer(_)) => return Ok(None),
+            SExpr::Atom(_) => None,
+            // The local bindings of a where clause only exist while it is
+            // evaluated, so the whole clause is reduced in one step
+            SExpr::Where(..) => None,
+            SExpr::Cons(op, operands) => {
+                // The lhs of an assignment is never evaluated
+                let skip = if matches!(op, SExprAtom::Op(op) if op.is_assignment()) {
+                    1
+                } else {
+                    0
+                };
+                // Only the condition of a conditional, or the lhs of a
+                // logical operator, is reduced on its own, as what else is
+                // evaluated depends on its value
+                let considered = if matches!(
+                    op,
+                    SExprAtom::Op(Operator::Question | Operator::And | Operator::Or)
+                ) {
+                    1
+                } else {
+                    operands.len()
+                };
+                let pending = operands
+                    .iter()
+                    .take(considered)
+                    .skip(skip)
+                    .position(|operand| {
+                        !matches!(
+                            operand,
+                            SExpr::Atom(SExprAtom::Number(_) | SExprAtom::Integer(_))
+                        )
+                    });
+                if let Some(index) = pending {
+                    return self.reduce_next(&mut operands[index + skip]);
+                }
+                match (op, operands.first()) {
+                    (SExprAtom::Op(op), Some(SExpr::Atom(SExprAtom::Variable(varname))))
+                        if op.is_assignment() =>
+                    {
+                        Some(varname.clone())
+                    }
+                    _ => None,
+                }
+            }
+        };
+        let current_node_infix = expr.to_infix();
+        let value = self.interpreter.interpret_sexpr(expr.clone())?;
+        *expr = SExpr::Atom(SExprAtom::Number(value));
+        Ok(Some(StepInfo {
+            current_node_infix,
+            partial_tree_infix: String::new(),
+            env_changes: assigned
+                .map(|varname| (varname, value))
+                .into_iter()
+                .collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test_debugger {
+    use super::*;
+    use crate::interpreter::interpreter::DivisionMode;
+
+    #[test]
+    fn test_step_through() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("x = 4")?;
+        let mut session = test_interpreter.debug("2 * 3 + x ^ 2")?;
+        let expected = [
+            ("2 * 3", "6 + (x ^ 2)"),
+            ("x", "6 + (4 ^ 2)"),
+            ("4 ^ 2", "6 + 16"),
+            ("6 + 16", "22"),
+        ];
+        for (current, partial) in expected {
+            assert_eq!(
+                session.step()?,
+                Some(StepInfo {
+                    current_node_infix: current.to_string(),
+                    partial_tree_infix: partial.to_string(),
+                    env_changes: Vec::new(),
+                })
+            );
+        }
+        assert_eq!(session.step()?, None);
+        assert_eq!(session.run_to_completion()?, 22f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_assignment_steps() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let mut session = test_interpreter.debug("y = (z = 1 + 2) * 2")?;
+        session.step()?;
+        let step = session.step()?;
+        assert_eq!(
+            step.map(|s| s.env_changes),
+            Some(vec![("z".to_string(), 3f64)])
+        );
+        // Abandon the session before y is assigned, z has already been
+        drop(session);
+        assert_eq!(test_interpreter.interpret("z")?, 3f64);
+        assert!(test_interpreter.interpret("y").is_err());
+
+        let session = test_interpreter.debug("y = z * 5")?;
+        assert_eq!(session.run_to_completion()?, 15f64);
+        assert_eq!(test_interpreter.interpret("y")?, 15f64);
+
+        // The variable being updated is left for the update to read
+        let mut session = test_interpreter.debug("y += z * 2")?;
+        session.step()?;
+        session.step()?;
+        let step = session.step()?;
+        assert_eq!(
+            step.map(|s| s.env_changes),
+            Some(vec![("y".to_string(), 21f64)])
+        );
+        assert_eq!(test_interpreter.interpret("y")?, 21f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conditional_steps() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let mut session = test_interpreter.debug("2 > 1 ? 3 * 4 : undefined")?;
+        let expected = [
+            ("2 > 1", "1 ? (3 * 4) : undefined"),
+            ("1 ? (3 * 4) : undefined", "12"),
+        ];
+        for (current, partial) in expected {
+            let step = session.step()?.unwrap();
+            assert_eq!(step.current_node_infix, current);
+            assert_eq!(step.partial_tree_infix, partial);
+        }
+        assert_eq!(session.step()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_integer_division_steps() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.set_division_mode(DivisionMode::Integer);
+        let mut session = test_interpreter.debug("(3 + 3) / 4")?;
+        // The reduced 6 divides like the 3 + 3 it replaced
+        let step = session.step()?.unwrap();
+        assert_eq!(step.partial_tree_infix, "6 / 4");
+        assert_eq!(session.run_to_completion()?, 1f64);
+        assert_eq!(test_interpreter.interpret("(3 + 3) / 4")?, 1f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_logical_steps() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let mut session = test_interpreter.debug("0 and (y = 5)")?;
+        let step = session.step()?.unwrap();
+        assert_eq!(step.current_node_infix, "0 && (y = 5)");
+        assert_eq!(step.partial_tree_infix, "0");
+        assert!(step.env_changes.is_empty());
+        assert_eq!(session.step()?, None);
+        assert!(!test_interpreter.is_defined("y"));
+
+        let mut session = test_interpreter.debug("1 + 1 or undefined")?;
+        let expected = [("1 + 1", "2 || undefined"), ("2 || undefined", "1")];
+        for (current, partial) in expected {
+            let step = session.step()?.unwrap();
+            assert_eq!(step.current_node_infix, current);
+            assert_eq!(step.partial_tree_infix, partial);
+        }
+        assert_eq!(session.step()?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Formatting of results for display
+// Standard Library Uses
+use std::fmt;
+
+// External Uses
+use anyhow::{Result, anyhow};
+
+/// Short scale names for powers of a thousand, used by the humane format
+const SCALE_WORDS: &[(f64, &str)] = &[
+    (1e3, "thousand"),
+    (1e6, "million"),
+    (1e9, "billion"),
+    (1e12, "trillion"),
+];
+
+/// Magnitude from which the humane format switches to scientific notation
+const HUMANE_SCIENTIFIC_THRESHOLD: f64 = 1e15;
+
+/// How numbers are written out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum NumberFormat {
+    /// Rust's shortest representation which round-trips, e.g. `3200000`
+    #[default]
+    Plain,
+    /// Words for large numbers, e.g. `3.2 million`
+    Humane,
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumberFormat::Plain => write!(f, "plain"),
+            NumberFormat::Humane => write!(f, "humane"),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Look up a format by its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "plain" => Ok(NumberFormat::Plain),
+            "humane" => Ok(NumberFormat::Humane),
+            _ => Err(anyhow!(
+                "Unknown format {name}, available formats are plain, humane"
+            )),
+        }
+    }
+
+    /// Write out a number in this format
+    pub fn format(&self, value: f64) -> String {
+        match self {
+            NumberFormat::Plain => format!("{value}"),
+            NumberFormat::Humane => format_humane(value),
+        }
+    }
+}
+
+/// How NaN and infinite values are written
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum SpecialValues {
+    /// As Rust writes them, `NaN`, `inf` and `-inf`
+    #[default]
+    Plain,
+    /// For readers who aren't programmers, `undefined`, `∞` and `-∞`
+    Readable,
+}
+
+impl fmt::Display for SpecialValues {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecialValues::Plain => write!(f, "plain"),
+            SpecialValues::Readable => write!(f, "readable"),
+        }
+    }
+}
+
+impl SpecialValues {
+    /// Look up a way of writing special values by its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "plain" => Ok(SpecialValues::Plain),
+            "readable" => Ok(SpecialValues::Readable),
+            _ => Err(anyhow!(
+                "Unknown special value style {name}, expected plain or readable"
+            )),
+        }
+    }
+
+    /// Write out a NaN or infinite value, None if the value is finite or
+    /// should be written by the number format as usual
+    pub fn format(&self, value: f64) -> Option<String> {
+        match self {
+            SpecialValues::Plain => None,
+            SpecialValues::Readable if value.is_nan() => Some("undefined".to_string()),
+            SpecialValues::Readable if value == f64::INFINITY => Some("∞".to_string()),
+            SpecialValues::Readable if value == f64::NEG_INFINITY => Some("-∞".to_string()),
+            SpecialValues::Readable => None,
+        }
+    }
+}
+
+/// Write a number with short scale words, to two decimal places
+///
+/// Magnitudes under a thousand are written plainly, and from 10^15 on in
+/// scientific notation. Trailing zeros are dropped, so two million is
+/// `2 million`, not `2.00 million`.
+fn format_humane(value: f64) -> String {
+    if !value.is_finite() || value.abs() < 1e3 {
+        return format!("{value}");
+    }
+    // Rounding can carry a value up into the next scale, so 999999 is
+    // `1 million` rather than `1000 thousand`
+    for (i, (scale, word)) in SCALE_WORDS.iter().enumerate() {
+        let next = SCALE_WORDS
+            .get(i + 1)
+            .map_or(HUMANE_SCIENTIFIC_THRESHOLD, |(next, _)| *next);
+        let scaled = round_to_hundredths(value / scale);
+        if scaled.abs() * scale < next {
+            return format!("{scaled} {word}");
+        }
+    }
+    let scientific = format!("{value:.2e}");
+    match scientific.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            format!("{mantissa}e{exponent}")
+        }
+        None => scientific,
+    }
+}
+
+/// Round to two decimal places
+fn round_to_hundredths(value: f64) -> f64 {
+    (value * 100f64).round() / 100f64
+}
+
+#[cfg(test)]
+mod test_format {
+    use super::*;
+
+    #[test]
+    fn test_humane_format() {
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0"),
+            (42.5, "42.5"),
+            (999.0, "999"),
+            (999.999, "999.999"),
+            (1000.0, "1 thousand"),
+            (-1000.0, "-1 thousand"),
+            (42_700.0, "42.7 thousand"),
+            (12_345.0, "12.35 thousand"),
+            (999_994.0, "999.99 thousand"),
+            (999_996.0, "1 million"),
+            (1e6, "1 million"),
+            (2e6, "2 million"),
+            (3_200_000.0, "3.2 million"),
+            (-3_200_000.0, "-3.2 million"),
+            (1.5e9, "1.5 billion"),
+            (1e12, "1 trillion"),
+            (999.99e12, "999.99 trillion"),
+            (999.999e12, "1e15"),
+            (1e15, "1e15"),
+            (1.234e16, "1.23e16"),
+            (-2.5e20, "-2.5e20"),
+            (f64::INFINITY, "inf"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(&NumberFormat::Humane.format(*value), expected);
+        }
+    }
+
+    #[test]
+    fn test_special_values() -> Result<()> {
+        let readable = SpecialValues::from_name("readable")?;
+        assert_eq!(readable.format(f64::NAN).as_deref(), Some("undefined"));
+        assert_eq!(readable.format(f64::INFINITY).as_deref(), Some("∞"));
+        assert_eq!(readable.format(f64::NEG_INFINITY).as_deref(), Some("-∞"));
+        assert_eq!(readable.format(2.5), None);
+        assert_eq!(SpecialValues::Plain.format(f64::NAN), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_names() -> Result<()> {
+        assert_eq!(NumberFormat::from_name("humane")?, NumberFormat::Humane);
+        assert_eq!(NumberFormat::Plain.format(3_200_000.0), "3200000");
+        let err = NumberFormat::from_name("words").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown format words, available formats are plain, humane"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,484 @@
+//! A machine readable description of the accepted grammar
+//!
+//! The description is built from the same tables the lexer, parser and
+//! interpreter use, so it can't drift from what is actually accepted. It
+//! can be written as JSON with [`GrammarDescription::to_json`] (and with
+//! serde when the `serde` feature is enabled), for editor plugins and
+//! documentation.
+// Standard Library Uses
+use std::fmt;
+
+// External Uses
+
+// Local Uses
+use super::interpreter::BUILTIN_FUNCTIONS;
+use super::lexer::{Keyword, Operator};
+use super::parser::{AssignmentStyle, BindingPowers, CONDITIONAL_BINDING_POWERS, PrattParser};
+
+/// Version of the description format, increased whenever its layout
+/// changes
+pub const GRAMMAR_VERSION: u32 = 2;
+
+/// Pairs of operators which open and close a group, with what the group
+/// computes
+const GROUPINGS: &[(Operator, Operator, &str)] = &[
+    (Operator::LParen, Operator::RParen, "grouping"),
+    (Operator::LFloor, Operator::RFloor, "floor"),
+    (Operator::LCeil, Operator::RCeil, "ceiling"),
+];
+
+/// A description of everything the calculator accepts
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GrammarDescription {
+    /// Version of the description format, see [`GRAMMAR_VERSION`]
+    pub version: u32,
+    /// The kinds of token other than operators
+    pub tokens: Vec<TokenDescription>,
+    /// Every prefix, infix and postfix operator
+    pub operators: Vec<OperatorDescription>,
+    /// Brackets which group an expression
+    pub groupings: Vec<GroupingDescription>,
+    /// Operators separating arguments, bindings and the branches of a
+    /// conditional
+    pub separators: Vec<&'static str>,
+    /// Reserved words
+    pub keywords: Vec<String>,
+    /// Built in functions
+    pub functions: Vec<FunctionDescription>,
+}
+
+/// A kind of token, with a regular expression matching it
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TokenDescription {
+    /// Name of the kind of token
+    pub name: &'static str,
+    /// Regular expression matching the token
+    pub pattern: &'static str,
+}
+
+/// Where an operator goes relative to its operands
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Fixity {
+    Prefix,
+    Infix,
+    Postfix,
+    Ternary,
+}
+
+/// Which way a chain of the same infix operator groups
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+/// An operator, with how tightly it binds
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OperatorDescription {
+    /// The operator as written
+    pub symbol: &'static str,
+    /// Where the operator goes relative to its operands
+    pub fixity: Fixity,
+    /// Precedence, operators with higher precedence bind more tightly
+    pub precedence: u8,
+    /// Binding power towards the operand on the left, if there is one
+    pub left_binding_power: Option<u8>,
+    /// Binding power towards the operand on the right, if there is one
+    pub right_binding_power: Option<u8>,
+    /// How a chain of the operator groups, for infix operators
+    pub associativity: Option<Associativity>,
+}
+
+/// A pair of brackets grouping an expression
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GroupingDescription {
+    /// The opening bracket
+    pub open: &'static str,
+    /// The closing bracket
+    pub close: &'static str,
+    /// What the group computes from its contents
+    pub meaning: &'static str,
+}
+
+/// A built in function
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FunctionDescription {
+    /// Name the function is called by
+    pub name: &'static str,
+    /// Fewest arguments the function takes
+    pub min_arity: usize,
+    /// Most arguments the function takes, None if there is no limit
+    pub max_arity: Option<usize>,
+}
+
+/// Describe the grammar accepted with the default binding powers
+pub fn describe() -> GrammarDescription {
+    describe_with(&BindingPowers::default())
+}
+
+/// Describe the grammar accepted with the given binding powers
+pub fn describe_with(binding_powers: &BindingPowers) -> GrammarDescription {
+    let mut operators = Vec::new();
+    for op in Operator::ALL {
+        if let Ok(((), right)) = PrattParser::prefix_binding_power(op) {
+            operators.push(OperatorDescription {
+                symbol: op.symbol(),
+                fixity: Fixity::Prefix,
+                precedence: right,
+                left_binding_power: None,
+                right_binding_power: Some(right),
+                associativity: None,
+            });
+        }
+    }
+    for (op, (left, right)) in binding_powers.infix_operators() {
+        // In the Pascal style := assigns and = compares like ==
+        let symbols: &[Operator] = match (binding_powers.assignment_style(), op) {
+            (AssignmentStyle::ColonEquals, Operator::Assign) => &[Operator::ColonAssign],
+            (AssignmentStyle::ColonEquals, Operator::Equal) => &[Operator::Equal, Operator::Assign],
+            _ => &[op],
+        };
+        operators.extend(symbols.iter().map(|symbol| OperatorDescription {
+            symbol: symbol.symbol(),
+            fixity: Fixity::Infix,
+            precedence: left.min(right),
+            left_binding_power: Some(left),
+            right_binding_power: Some(right),
+            associativity: Some(if left < right {
+                Associativity::Left
+            } else {
+                Associativity::Right
+            }),
+        }));
+    }
+    let (left, right) = CONDITIONAL_BINDING_POWERS;
+    operators.push(OperatorDescription {
+        symbol: Operator::Question.symbol(),
+        fixity: Fixity::Ternary,
+        precedence: left.min(right),
+        left_binding_power: Some(left),
+        right_binding_power: Some(right),
+        associativity: Some(Associativity::Right),
+    });
+    for op in Operator::ALL {
+        if let Some((left, ())) = PrattParser::postfix_binding_power(op) {
+            operators.push(OperatorDescription {
+                symbol: op.symbol(),
+                fixity: Fixity::Postfix,
+                precedence: left,
+                left_binding_power: Some(left),
+                right_binding_power: None,
+                associativity: None,
+            });
+        }
+    }
+    GrammarDescription {
+        version: GRAMMAR_VERSION,
+        tokens: vec![
+            TokenDescription {
+                name: "number",
+                pattern: r"[0-9](_?[0-9])*(_?\.[0-9](_?[0-9])*)?([eE][+-]?[0-9](_?[0-9])*)?",
+            },
+            TokenDescription {
+                name: "radix number",
+                pattern: r"0([xX][0-9a-fA-F](_?[0-9a-fA-F])*|[oO][0-7](_?[0-7])*|[bB][01](_?[01])*)",
+            },
+            TokenDescription {
+                name: "percentage",
+                pattern: r"<number>%",
+            },
+            TokenDescription {
+                name: "permille",
+                pattern: r"<number>‰",
+            },
+            TokenDescription {
+                name: "variable",
+                pattern: r"[\p{Alphabetic}_][\p{Alphabetic}\p{N}_]*",
+            },
+            TokenDescription {
+                name: "whitespace",
+                pattern: r"\s+",
+            },
+        ],
+        operators,
+        groupings: GROUPINGS
+            .iter()
+            .map(|(open, close, meaning)| GroupingDescription {
+                open: open.symbol(),
+                close: close.symbol(),
+                meaning,
+            })
+            .collect(),
+        separators: vec![Operator::Comma.symbol(), Operator::Colon.symbol()],
+        keywords: Keyword::ALL.iter().map(Keyword::to_string).collect(),
+        functions: BUILTIN_FUNCTIONS
+            .iter()
+            .map(|&(name, min_arity, max_arity)| FunctionDescription {
+                name,
+                min_arity,
+                max_arity,
+            })
+            .collect(),
+    }
+}
+
+impl GrammarDescription {
+    /// Write the description as pretty printed JSON
+    pub fn to_json(&self) -> String {
+        let tokens = self
+            .tokens
+            .iter()
+            .map(|token| {
+                format!(
+                    "{{\"name\": {}, \"pattern\": {}}}",
+                    json_string(token.name),
+                    json_string(token.pattern)
+                )
+            })
+            .collect::<Vec<String>>();
+        let operators = self
+            .operators
+            .iter()
+            .map(|op| {
+                format!(
+                    "{{\"symbol\": {}, \"fixity\": \"{}\", \"precedence\": {}, \"left_binding_power\": {}, \"right_binding_power\": {}, \"associativity\": {}}}",
+                    json_string(op.symbol),
+                    op.fixity,
+                    op.precedence,
+                    json_option(op.left_binding_power),
+                    json_option(op.right_binding_power),
+                    json_option(op.associativity.map(|a| json_string(&a.to_string()))),
+                )
+            })
+            .collect::<Vec<String>>();
+        let groupings = self
+            .groupings
+            .iter()
+            .map(|group| {
+                format!(
+                    "{{\"open\": {}, \"close\": {}, \"meaning\": {}}}",
+                    json_string(group.open),
+                    json_string(group.close),
+                    json_string(group.meaning)
+                )
+            })
+            .collect::<Vec<String>>();
+        let separators = self
+            .separators
+            .iter()
+            .map(|separator| json_string(separator))
+            .collect::<Vec<String>>();
+        let keywords = self
+            .keywords
+            .iter()
+            .map(|keyword| json_string(keyword))
+            .collect::<Vec<String>>();
+        let functions = self
+            .functions
+            .iter()
+            .map(|function| {
+                format!(
+                    "{{\"name\": {}, \"min_arity\": {}, \"max_arity\": {}}}",
+                    json_string(function.name),
+                    function.min_arity,
+                    json_option(function.max_arity)
+                )
+            })
+            .collect::<Vec<String>>();
+        let sections = [
+            ("tokens", tokens),
+            ("operators", operators),
+            ("groupings", groupings),
+            ("separators", separators),
+            ("keywords", keywords),
+            ("functions", functions),
+        ]
+        .map(|(name, items)| {
+            format!(
+                "  \"{name}\": [\n{}\n  ]",
+                items
+                    .iter()
+                    .map(|item| format!("    {item}"))
+                    .collect::<Vec<String>>()
+                    .join(",\n")
+            )
+        });
+        format!(
+            "{{\n  \"version\": {},\n{}\n}}",
+            self.version,
+            sections.join(",\n")
+        )
+    }
+}
+
+impl fmt::Display for Fixity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fixity::Prefix => write!(f, "prefix"),
+            Fixity::Infix => write!(f, "infix"),
+            Fixity::Postfix => write!(f, "postfix"),
+            Fixity::Ternary => write!(f, "ternary"),
+        }
+    }
+}
+
+impl fmt::Display for Associativity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Associativity::Left => write!(f, "left"),
+            Associativity::Right => write!(f, "right"),
+        }
+    }
+}
+
+/// Write text as a JSON string literal
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Write an optional value as JSON, null if there isn't one
+fn json_option<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod test_grammar {
+    use super::*;
+    use anyhow::Result;
+
+    /// The infix operators of a description
+    fn infix(description: &GrammarDescription) -> Vec<&OperatorDescription> {
+        description
+            .operators
+            .iter()
+            .filter(|op| op.fixity == Fixity::Infix)
+            .collect()
+    }
+
+    #[test]
+    fn test_precedence_matches_parser() -> Result<()> {
+        let description = describe();
+        let operators = infix(&description);
+        for first in &operators {
+            for second in &operators {
+                // With a higher precedence operator on either side, it is
+                // the one which groups first
+                let parsed =
+                    PrattParser::parse(&format!("a {} b {} c", first.symbol, second.symbol))?
+                        .to_string();
+                let left_grouped = format!("({} ({} a b) c)", second.symbol, first.symbol);
+                let right_grouped = format!("({} a ({} b c))", first.symbol, second.symbol);
+                let expected = if first.precedence != second.precedence {
+                    if first.precedence > second.precedence {
+                        &left_grouped
+                    } else {
+                        &right_grouped
+                    }
+                } else if first.associativity == Some(Associativity::Left) {
+                    &left_grouped
+                } else {
+                    &right_grouped
+                };
+                assert_eq!(
+                    &parsed, expected,
+                    "a {} b {} c",
+                    first.symbol, second.symbol
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_describes_every_operator() -> Result<()> {
+        let description = describe();
+        // Prefix operators bind more tightly than any infix operator
+        let minus = description
+            .operators
+            .iter()
+            .find(|op| op.symbol == "-" && op.fixity == Fixity::Prefix)
+            .unwrap();
+        assert_eq!(PrattParser::parse("-a ^ b")?.to_string(), "(^ (- a) b)");
+        assert!(
+            infix(&description)
+                .iter()
+                .all(|op| op.precedence < minus.precedence)
+        );
+        // Every operator is either described or a grouping or separator,
+        // apart from := which only assigns in the Pascal style
+        for op in Operator::ALL
+            .iter()
+            .filter(|&&op| op != Operator::ColonAssign)
+        {
+            let symbol = op.symbol();
+            assert!(
+                description.operators.iter().any(|o| o.symbol == symbol)
+                    || description
+                        .groupings
+                        .iter()
+                        .any(|g| g.open == symbol || g.close == symbol)
+                    || description.separators.contains(&symbol),
+                "{symbol} is not described"
+            );
+        }
+        assert_eq!(description.keywords, vec!["where", "and", "or", "not"]);
+
+        let mut binding_powers = BindingPowers::default();
+        binding_powers.set_assignment_style(AssignmentStyle::ColonEquals);
+        let pascal = describe_with(&binding_powers);
+        let precedence = |symbol: &str| {
+            infix(&pascal)
+                .iter()
+                .find(|op| op.symbol == symbol)
+                .map(|op| op.precedence)
+        };
+        assert_eq!(precedence(":="), Some(1));
+        assert_eq!(precedence("="), precedence("=="));
+        assert!(description.functions.contains(&FunctionDescription {
+            name: "sqrt",
+            min_arity: 1,
+            max_arity: Some(1)
+        }));
+        assert!(description.functions.contains(&FunctionDescription {
+            name: "log10",
+            min_arity: 1,
+            max_arity: Some(1)
+        }));
+        assert!(description.functions.contains(&FunctionDescription {
+            name: "max",
+            min_arity: 1,
+            max_arity: None
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() {
+        let json = describe().to_json();
+        assert!(json.starts_with("{\n  \"version\": 2,\n  \"tokens\": [\n"));
+        assert!(json.contains(
+            "{\"symbol\": \"^\", \"fixity\": \"infix\", \"precedence\": 19, \"left_binding_power\": 20, \"right_binding_power\": 19, \"associativity\": \"right\"}"
+        ));
+        assert!(json.contains("{\"name\": \"random\", \"min_arity\": 0, \"max_arity\": 0}"));
+        assert!(json.contains("{\"name\": \"max\", \"min_arity\": 1, \"max_arity\": null}"));
+        assert!(json.contains("{\"name\": \"whitespace\", \"pattern\": \"\\\\s+\"}"));
+        assert_eq!(json_string("a\"b\n"), "\"a\\\"b\\u000a\"");
+    }
+}
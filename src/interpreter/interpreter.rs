@@ -1,41 +1,600 @@
 //! Implementation of a Tree-Walk interpreter
 // Standard Library Uses
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, RandomState};
+use std::panic::{self, AssertUnwindSafe};
 
 // External Uses
 use anyhow::{Context, Result, anyhow};
 
 // Local Uses
-use super::parser::{PrattParser, SExpr, SExprAtom};
+use super::format::{NumberFormat, SpecialValues};
+use super::lexer::Operator;
+use super::parser::{AssignmentStyle, BindingPowers, PrattParser, SExpr, SExprAtom};
 
 /// A Tree Walk interpreter
-pub(crate) struct Interpreter {
+pub struct Interpreter {
     environment: HashMap<String, f64>,
+    /// Number of evaluation steps (S-expression visits) taken
+    steps: u64,
+    /// Calculator style memory register, read in expressions as `MR`
+    memory: f64,
+    /// The result of the last successful evaluation, read in expressions
+    /// as `ans`, None until there has been one
+    answer: Option<f64>,
+    /// How `/` treats whole number operands
+    division_mode: DivisionMode,
+    /// How `round` breaks ties
+    rounding: RoundingMode,
+    /// Whether to refuse storing NaN or infinite values in variables
+    quarantine_nonfinite: bool,
+    /// How results are written out
+    number_format: NumberFormat,
+    /// How NaN and infinite results are written out
+    special_values: SpecialValues,
+    /// Message of the error from the last call to `interpret`, None if it
+    /// succeeded
+    last_error: Option<String>,
+    /// Parse tree of the last statement parsed by the last call to
+    /// `interpret`, None if nothing parsed
+    last_parse_tree: Option<SExpr>,
+    /// Binding powers of the infix operators used to parse input
+    binding_powers: BindingPowers,
+    /// Functions which can be called by name, starting with the
+    /// [`BUILTIN_FUNCTIONS`]
+    builtins: HashMap<String, Builtin>,
 }
 
+/// A function callable by name in expressions, taking the values of its
+/// arguments
+pub type BuiltinFunction = Box<dyn Fn(&[f64]) -> Result<f64>>;
+
+/// Implementation of one of the [`BUILTIN_FUNCTIONS`], given the
+/// interpreter calling it
+type NativeFunction = fn(&Interpreter, &[f64]) -> Result<f64>;
+
+/// A function callable by name along with the number of arguments it takes
+struct Builtin {
+    /// Fewest arguments the function takes
+    min_arity: usize,
+    /// Most arguments the function takes, None if there is no limit
+    max_arity: Option<usize>,
+    function: Implementation,
+}
+
+/// How a function callable by name is implemented
+enum Implementation {
+    /// One of the [`BUILTIN_FUNCTIONS`]
+    Native(NativeFunction),
+    /// A function added with [`Interpreter::register_builtin`]
+    Custom(BuiltinFunction),
+}
+
+/// How `/` treats whole number operands
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum DivisionMode {
+    /// Always divide normally, `7/2` is 3.5
+    #[default]
+    Float,
+    /// Truncate the quotient when the values of both operands are whole,
+    /// however they are written, so `7/2`, `7.0/2` and `x/2` with `x = 7`
+    /// are all 3, and `7.5/2` is 3.75
+    Integer,
+}
+
+impl fmt::Display for DivisionMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DivisionMode::Float => write!(f, "float"),
+            DivisionMode::Integer => write!(f, "int"),
+        }
+    }
+}
+
+impl DivisionMode {
+    /// Look up a division mode by its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "float" => Ok(DivisionMode::Float),
+            "int" => Ok(DivisionMode::Integer),
+            _ => Err(anyhow!(
+                "Unknown division mode {name}, expected int or float"
+            )),
+        }
+    }
+}
+
+/// How ties are broken when rounding
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum RoundingMode {
+    /// Round ties away from zero, `2.5` to 3 and `-2.5` to -3
+    #[default]
+    HalfUp,
+    /// Round ties to the nearest even digit, `2.5` to 2 and `3.5` to 4
+    HalfEven,
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundingMode::HalfUp => write!(f, "half_up"),
+            RoundingMode::HalfEven => write!(f, "half_even"),
+        }
+    }
+}
+
+impl RoundingMode {
+    /// Look up a rounding mode by its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "half_up" => Ok(RoundingMode::HalfUp),
+            "half_even" => Ok(RoundingMode::HalfEven),
+            _ => Err(anyhow!(
+                "Unknown rounding mode {name}, expected half_up or half_even"
+            )),
+        }
+    }
+}
+
+/// The result of evaluating a program along with its display form
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalResult {
+    /// The numerical value of the result
+    pub value: f64,
+    /// Whether the value is a whole number
+    pub is_integral: bool,
+    /// The value formatted for display, with the interpreter's
+    /// [`NumberFormat`] and [`SpecialValues`]
+    pub display: String,
+    /// Whether the program ended with a `;`, so the result shouldn't be shown
+    pub suppressed: bool,
+}
+
+impl EvalResult {
+    /// Create a new result from a value and how it is displayed
+    pub(super) fn new(value: f64, display: String, suppressed: bool) -> Self {
+        EvalResult {
+            value,
+            is_integral: value.is_finite() && value.fract() == 0f64,
+            display,
+            suppressed,
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The built in functions, with the fewest and most arguments each takes,
+/// the most being None if there is no limit
+pub const BUILTIN_FUNCTIONS: &[(&str, usize, Option<usize>)] = &[
+    ("abs", 1, Some(1)),
+    ("ceil", 1, Some(1)),
+    ("cos", 1, Some(1)),
+    ("exp", 1, Some(1)),
+    ("floor", 1, Some(1)),
+    ("ln", 1, Some(1)),
+    ("log", 1, Some(2)),
+    ("log10", 1, Some(1)),
+    ("max", 1, None),
+    ("min", 1, None),
+    ("random", 0, Some(0)),
+    ("round", 1, Some(2)),
+    ("round_half_even", 1, Some(2)),
+    ("round_half_up", 1, Some(2)),
+    ("sin", 1, Some(1)),
+    ("sqrt", 1, Some(1)),
+    ("tan", 1, Some(1)),
+    ("ulp", 1, Some(1)),
+];
+
+/// Largest operand whose factorial is finite as an f64
+const MAX_FACTORIAL_OPERAND: u32 = 170;
+
+/// How far the operand of a factorial can be from a whole number and
+/// still be treated as one
+const FACTORIAL_TOLERANCE: f64 = 1e-9;
+
+/// Variables every interpreter starts with, which can be reassigned like
+/// any other
+pub const CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("tau", std::f64::consts::TAU),
+    ("inf", f64::INFINITY),
+];
+
+/// Name which reads the memory register in expressions
+pub const MEMORY_RECALL: &str = "MR";
+
+/// Name which reads the previous result in expressions
+pub const ANSWER: &str = "ans";
+
 impl Interpreter {
-    /// Create a new interpreter with an empty environment
-    pub(crate) fn new() -> Self {
-        Interpreter {
+    /// Create a new interpreter with only the [`CONSTANTS`] in its
+    /// environment
+    pub fn new() -> Self {
+        let mut interpreter = Interpreter {
             environment: HashMap::new(),
+            steps: 0,
+            memory: 0f64,
+            answer: None,
+            division_mode: DivisionMode::default(),
+            rounding: RoundingMode::default(),
+            quarantine_nonfinite: false,
+            number_format: NumberFormat::default(),
+            special_values: SpecialValues::default(),
+            last_error: None,
+            last_parse_tree: None,
+            binding_powers: BindingPowers::default(),
+            builtins: BUILTIN_FUNCTIONS
+                .iter()
+                .filter_map(|&(name, min_arity, max_arity)| {
+                    builtin_implementation(name).map(|function| {
+                        let builtin = Builtin {
+                            min_arity,
+                            max_arity,
+                            function: Implementation::Native(function),
+                        };
+                        (name.to_string(), builtin)
+                    })
+                })
+                .collect(),
+        };
+        interpreter.reset_variables();
+        interpreter
+    }
+
+    /// Add a function which can be called by name, replacing any existing
+    /// function with that name. The function is given however many
+    /// arguments it is called with, so should check their number itself.
+    /// If it panics the call fails with an error naming the function,
+    /// leaving the interpreter usable.
+    pub fn register_builtin(
+        &mut self,
+        name: &str,
+        function: impl Fn(&[f64]) -> Result<f64> + 'static,
+    ) {
+        let function_name = name.to_string();
+        let builtin = Builtin {
+            min_arity: 0,
+            max_arity: None,
+            function: Implementation::Custom(Box::new(move |arguments: &[f64]| {
+                panic::catch_unwind(AssertUnwindSafe(|| function(arguments))).unwrap_or_else(
+                    |payload| {
+                        // Panics carry a &str or String message, unless
+                        // raised with panic_any
+                        let message = payload
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                            .or_else(|| payload.downcast_ref::<String>().cloned())
+                            .unwrap_or_else(|| "no message".to_string());
+                        Err(anyhow!("Function {function_name} panicked: {message}"))
+                    },
+                )
+            })),
+        };
+        self.builtins.insert(name.to_string(), builtin);
+    }
+
+    /// Call the function name with the given arguments
+    fn call_builtin(&self, name: &str, arguments: &[f64]) -> Result<f64> {
+        match self.builtins.get(name) {
+            Some(builtin) => {
+                check_arity(name, builtin.min_arity, builtin.max_arity, arguments.len())?;
+                match &builtin.function {
+                    Implementation::Native(function) => function(self, arguments),
+                    Implementation::Custom(function) => function(arguments),
+                }
+            }
+            None => {
+                let mut names = self.builtins.keys().cloned().collect::<Vec<String>>();
+                names.sort();
+                Err(anyhow!(
+                    "Unknown function {name}, the available functions are {}",
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// The binding powers of the infix operators used to parse input
+    pub fn binding_powers(&self) -> &BindingPowers {
+        &self.binding_powers
+    }
+
+    /// Replace the binding powers, and assignment style, used to parse
+    /// input from now on
+    pub fn set_binding_powers(&mut self, binding_powers: BindingPowers) {
+        self.binding_powers = binding_powers;
+    }
+
+    /// Change the binding powers of an infix operator for input parsed
+    /// from now on
+    pub fn set_binding_power(&mut self, op: Operator, left: u8, right: u8) -> Result<()> {
+        self.binding_powers.set_infix(op, left, right)
+    }
+
+    /// Change which operator assigns to a variable for input parsed from
+    /// now on
+    pub fn set_assignment_style(&mut self, style: AssignmentStyle) {
+        self.binding_powers.set_assignment_style(style);
+    }
+
+    /// Set whether NaN and infinite values are refused when storing
+    /// variables, non-finite intermediate values are still allowed
+    pub fn set_quarantine_nonfinite(&mut self, quarantine: bool) {
+        self.quarantine_nonfinite = quarantine;
+    }
+
+    /// Whether NaN and infinite values are refused when storing variables
+    pub fn quarantine_nonfinite(&self) -> bool {
+        self.quarantine_nonfinite
+    }
+
+    /// Set how results are written out in [`EvalResult::display`]
+    pub fn set_number_format(&mut self, format: NumberFormat) {
+        self.number_format = format;
+    }
+
+    /// How results are written out
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+
+    /// Set how NaN and infinite results are written out in
+    /// [`EvalResult::display`]
+    pub fn set_special_values(&mut self, special_values: SpecialValues) {
+        self.special_values = special_values;
+    }
+
+    /// How NaN and infinite results are written out
+    pub fn special_values(&self) -> SpecialValues {
+        self.special_values
+    }
+
+    /// Write out a value with the current format and special values
+    pub fn format_value(&self, value: f64) -> String {
+        self.special_values
+            .format(value)
+            .unwrap_or_else(|| self.number_format.format(value))
+    }
+
+    /// Check whether a variable (or the memory register, or `ans`) has
+    /// a value
+    pub fn is_defined(&self, name: &str) -> bool {
+        name == MEMORY_RECALL
+            || (name == ANSWER && self.answer.is_some())
+            || self.environment.contains_key(name)
+    }
+
+    /// Set how `/` treats whole number operands
+    pub fn set_division_mode(&mut self, mode: DivisionMode) {
+        self.division_mode = mode;
+    }
+
+    /// How `/` treats whole number operands
+    pub fn division_mode(&self) -> DivisionMode {
+        self.division_mode
+    }
+
+    /// Set how `round` breaks ties, `round_half_up` and `round_half_even`
+    /// always use their own
+    pub fn set_rounding(&mut self, mode: RoundingMode) {
+        self.rounding = mode;
+    }
+
+    /// How `round` breaks ties
+    pub fn rounding(&self) -> RoundingMode {
+        self.rounding
+    }
+
+    /// The result of the last successful evaluation, read as `ans`
+    pub fn answer(&self) -> Option<f64> {
+        self.answer
+    }
+
+    /// Set the value read as `ans`, for results produced outside of
+    /// [`Interpreter::interpret_full`]
+    pub fn set_answer(&mut self, value: f64) {
+        self.answer = Some(value);
+    }
+
+    /// Set the value of a variable
+    pub fn set_variable(&mut self, name: &str, value: f64) -> Result<()> {
+        self.store_variable(name.to_string(), value, "set_variable")
+    }
+
+    /// The value of a variable, or None if it hasn't been assigned
+    pub fn variable(&self, name: &str) -> Option<f64> {
+        self.environment.get(name).copied()
+    }
+
+    /// Every variable with a value, sorted by name
+    pub fn variables(&self) -> Vec<(String, f64)> {
+        let mut variables = self
+            .environment
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect::<Vec<_>>();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
+    }
+
+    /// Remove every variable, putting back the [`CONSTANTS`] as a new
+    /// interpreter has them
+    pub fn reset_variables(&mut self) {
+        self.environment = CONSTANTS
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+    }
+
+    /// Store a value in a variable, source describes what produced the
+    /// value for the error if it is refused
+    fn store_variable(&mut self, name: String, value: f64, source: &str) -> Result<()> {
+        if name == ANSWER {
+            return Err(anyhow!(
+                "Can't assign to {ANSWER}, it always holds the previous result"
+            ));
+        }
+        if self.quarantine_nonfinite && !value.is_finite() {
+            return Err(anyhow!(
+                "Refusing to store non-finite value {value} in {name}, produced by {source}"
+            ));
         }
+        self.environment.insert(name, value);
+        Ok(())
+    }
+
+    /// Add a value to the memory register, returning the new memory value
+    pub fn memory_add(&mut self, value: f64) -> f64 {
+        self.memory += value;
+        self.memory
     }
 
-    /// Interpret a program represented as a string
-    pub(crate) fn interpret(&mut self, input: &str) -> Result<f64> {
-        let program_sexpr = PrattParser::parse(input)
+    /// Clear the memory register
+    pub fn memory_clear(&mut self) {
+        self.memory = 0f64;
+    }
+
+    /// The message of the error from the last call to
+    /// [`Interpreter::interpret`] (or the functions built on it),
+    /// including its context, or None if that call succeeded
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// The parse tree of the last statement parsed by the last call to
+    /// [`Interpreter::interpret`] (or the functions built on it), kept even
+    /// if evaluating it failed, or None if nothing parsed
+    pub fn last_parse_tree(&self) -> Option<&SExpr> {
+        self.last_parse_tree.as_ref()
+    }
+
+    /// Interpret a program represented as a string, made up of one or
+    /// more `;` separated statements, returning the value of the last one
+    pub fn interpret(&mut self, input: &str) -> Result<f64> {
+        self.last_parse_tree = None;
+        let res = self.interpret_program(input);
+        self.last_error = res.as_ref().err().map(|err| format!("{err:#}"));
+        res
+    }
+
+    /// Interpret each statement of a program, returning the value of the
+    /// last one
+    fn interpret_program(&mut self, input: &str) -> Result<f64> {
+        let statements = Self::split_statements(input);
+        let Some((last, rest)) = statements.split_last() else {
+            return self.interpret_statement(input);
+        };
+        for statement in rest {
+            self.interpret_statement(statement)?;
+        }
+        self.interpret_statement(last)
+    }
+
+    /// Interpret a program represented as a string, returning the value
+    /// along with how it should be displayed
+    ///
+    /// A program ending in `;` is still evaluated, but its result is
+    /// marked as suppressed so frontends don't print it. The result
+    /// becomes the value of `ans`.
+    pub fn interpret_full(&mut self, input: &str) -> Result<EvalResult> {
+        let value = self.interpret(input)?;
+        self.answer = Some(value);
+        Ok(EvalResult::new(
+            value,
+            self.format_value(value),
+            input.trim_end().ends_with(';'),
+        ))
+    }
+
+    /// Interpret a program with some variables temporarily bound to the
+    /// given values, restoring their previous values afterwards
+    pub fn interpret_with(&mut self, input: &str, bindings: &[(&str, f64)]) -> Result<f64> {
+        let previous = bindings
+            .iter()
+            .map(|(name, value)| (*name, self.environment.insert(name.to_string(), *value)))
+            .collect::<Vec<_>>();
+        let res = self.interpret(input);
+        for (name, value) in previous.into_iter().rev() {
+            match value {
+                Some(value) => self.environment.insert(name.to_string(), value),
+                None => self.environment.remove(name),
+            };
+        }
+        res
+    }
+
+    /// Interpret each `;` separated statement of a program, continuing
+    /// past statements which fail
+    pub fn interpret_each(&mut self, input: &str) -> Vec<Result<f64>> {
+        Self::split_statements(input)
+            .into_iter()
+            .map(|statement| self.interpret_statement(statement))
+            .collect()
+    }
+
+    /// Check that each `;` separated statement of a program parses,
+    /// without evaluating any of them
+    pub fn check(&self, input: &str) -> Result<()> {
+        let statements = Self::split_statements(input);
+        if statements.is_empty() {
+            return Err(anyhow!("Empty input, expected an expression"));
+        }
+        for statement in statements {
+            PrattParser::parse_with(statement, &self.binding_powers)
+                .with_context(|| format!("Failed to parse {}", statement.trim()))?;
+        }
+        Ok(())
+    }
+
+    /// Split a program into its non-empty `;` separated statements
+    fn split_statements(input: &str) -> Vec<&str> {
+        input
+            .split(';')
+            .filter(|statement| !statement.trim().is_empty())
+            .collect()
+    }
+
+    /// Interpret a single statement represented as a string
+    fn interpret_statement(&mut self, input: &str) -> Result<f64> {
+        let program_sexpr = PrattParser::parse_with(input, &self.binding_powers)
             .context("Trying to parse input into S-expression for interpretation")?;
+        self.last_parse_tree = Some(program_sexpr.clone());
         self.interpret_sexpr(program_sexpr)
     }
 
+    /// Interpret a program represented as a string, also returning the
+    /// number of evaluation steps it took
+    pub fn interpret_metered(&mut self, input: &str) -> Result<(f64, u64)> {
+        self.steps = 0;
+        let res = self.interpret(input)?;
+        Ok((res, self.steps))
+    }
+
     /// Interpret an S-expression, returning a numerical value, or an error
-    fn interpret_sexpr(&mut self, expr: SExpr) -> Result<f64> {
+    pub fn interpret_sexpr(&mut self, expr: SExpr) -> Result<f64> {
+        self.steps += 1;
         match expr {
             SExpr::Atom(at) => match at {
                 SExprAtom::Op(_) => Err(anyhow!(
                     "Encountered operator as S-expression atom with no operands"
                 )),
                 SExprAtom::Number(num) => Ok(num),
+                SExprAtom::Integer(num) => Ok(num as f64),
+                SExprAtom::Variable(varname) if varname == MEMORY_RECALL => Ok(self.memory),
+                SExprAtom::Variable(varname) if varname == ANSWER => self
+                    .answer
+                    .ok_or(anyhow!("No previous result available for {ANSWER}")),
                 SExprAtom::Variable(varname) => match self.environment.get(&varname) {
                     Some(val) => Ok(val.to_owned()),
                     None => Err(anyhow!("Tried to access variable with no value assigned")),
@@ -44,7 +603,7 @@ impl Interpreter {
             SExpr::Cons(operator, mut operands) => match operator {
                 SExprAtom::Op(op) => match op {
                     // Match prefix operators
-                    '+' | '-' if operands.len() == 1 => {
+                    Operator::Plus | Operator::Minus if operands.len() == 1 => {
                         let operand_value = match operands.pop() {
                             Some(val) => val,
                             None => {
@@ -54,9 +613,9 @@ impl Interpreter {
                             }
                         };
                         Ok(self.interpret_sexpr(operand_value)?
-                            * (if op == '+' {
+                            * (if op == Operator::Plus {
                                 1f64 // Prefix + is a no-op
-                            } else if op == '-' {
+                            } else if op == Operator::Minus {
                                 -1f64 // Multiply by -1
                             } else {
                                 // This should never happen
@@ -66,7 +625,21 @@ impl Interpreter {
                             }))
                     }
                     // Match Binary Operators (excluding assignment)
-                    '+' | '-' | '*' | '/' | '^' if operands.len() == 2 => {
+                    Operator::Plus
+                    | Operator::Minus
+                    | Operator::Star
+                    | Operator::Slash
+                    | Operator::DoubleSlash
+                    | Operator::Percent
+                    | Operator::Caret
+                    | Operator::Equal
+                    | Operator::NotEqual
+                    | Operator::Less
+                    | Operator::LessEqual
+                    | Operator::Greater
+                    | Operator::GreaterEqual
+                        if operands.len() == 2 =>
+                    {
                         // Extract the operands
                         let rhs = match operands.pop() {
                             Some(val) => val,
@@ -85,6 +658,8 @@ impl Interpreter {
                                 ));
                             }
                         };
+                        // A percentage added or subtracted is relative to the lhs
+                        let relative_percent = rhs.is_percent();
                         // Evaluate the operands
                         let lhs_value = self
                             .interpret_sexpr(lhs)
@@ -95,72 +670,508 @@ impl Interpreter {
 
                         // Now compute the result
                         let res = match op {
-                            '+' => lhs_value + rhs_value,
-                            '-' => lhs_value - rhs_value,
-                            '*' => lhs_value * rhs_value,
-                            '/' => lhs_value / rhs_value,
-                            '^' => lhs_value.powf(rhs_value),
+                            Operator::Plus if relative_percent => lhs_value + lhs_value * rhs_value,
+                            Operator::Minus if relative_percent => {
+                                lhs_value - lhs_value * rhs_value
+                            }
+                            Operator::Plus => lhs_value + rhs_value,
+                            Operator::Minus => lhs_value - rhs_value,
+                            Operator::Star => lhs_value * rhs_value,
+                            Operator::Slash
+                                if self.division_mode == DivisionMode::Integer
+                                    && lhs_value.fract() == 0f64
+                                    && rhs_value.fract() == 0f64 =>
+                            {
+                                (lhs_value / rhs_value).trunc()
+                            }
+                            Operator::Slash => lhs_value / rhs_value,
+                            Operator::DoubleSlash if rhs_value == 0f64 => {
+                                return Err(anyhow!("Can't floor divide {lhs_value} by zero"));
+                            }
+                            Operator::DoubleSlash => (lhs_value / rhs_value).floor(),
+                            Operator::Percent if rhs_value == 0f64 => {
+                                return Err(anyhow!(
+                                    "Can't take the remainder of {lhs_value} divided by zero"
+                                ));
+                            }
+                            // The result takes the sign of a positive divisor,
+                            // rather than of the dividend like f64's %
+                            Operator::Percent if rhs_value > 0f64 => {
+                                ((lhs_value % rhs_value) + rhs_value) % rhs_value
+                            }
+                            Operator::Percent => lhs_value % rhs_value,
+                            Operator::Caret => lhs_value.powf(rhs_value),
+                            // Comparisons give 1 for true and 0 for false
+                            Operator::Equal => f64::from(lhs_value == rhs_value),
+                            Operator::NotEqual => f64::from(lhs_value != rhs_value),
+                            Operator::Less => f64::from(lhs_value < rhs_value),
+                            Operator::LessEqual => f64::from(lhs_value <= rhs_value),
+                            Operator::Greater => f64::from(lhs_value > rhs_value),
+                            Operator::GreaterEqual => f64::from(lhs_value >= rhs_value),
                             _ => return Err(anyhow!("Encountered invalid binary operator {op}")),
                         };
 
                         // Return the result of the computation
                         Ok(res)
                     }
+                    // Logical operators only evaluate their rhs if the lhs
+                    // doesn't already decide the result
+                    Operator::And | Operator::Or if operands.len() == 2 => {
+                        let (Some(rhs), Some(lhs)) = (operands.pop(), operands.pop()) else {
+                            return Err(anyhow!("Unable to extract operands of {op}"));
+                        };
+                        let lhs_value = self
+                            .interpret_sexpr(lhs)
+                            .with_context(|| format!("Failed to evaluate lhs of {op}"))?;
+                        match (op, lhs_value != 0f64) {
+                            (Operator::And, false) => Ok(0f64),
+                            (Operator::Or, true) => Ok(1f64),
+                            _ => {
+                                let rhs_value = self
+                                    .interpret_sexpr(rhs)
+                                    .with_context(|| format!("Failed to evaluate rhs of {op}"))?;
+                                Ok(f64::from(rhs_value != 0f64))
+                            }
+                        }
+                    }
                     // Match the assignment operator
-                    '=' if operands.len() == 2 => {
-                        let rhs = match operands.pop() {
-                            Some(sexpr) => self
-                                .interpret_sexpr(sexpr)
-                                .context("Unable to evaluate rhs of assignment")?,
+                    Operator::Assign if operands.len() == 2 => {
+                        let (rhs, rhs_source) = match operands.pop() {
+                            Some(sexpr) => {
+                                let rhs_source = sexpr.to_string();
+                                let rhs = self
+                                    .interpret_sexpr(sexpr)
+                                    .context("Unable to evaluate rhs of assignment")?;
+                                (rhs, rhs_source)
+                            }
                             None => return Err(anyhow!("Assignment operator had no operands")),
                         };
                         match operands.pop() {
                             Some(sexpr) => match sexpr {
                                 SExpr::Atom(at) => match at {
+                                    SExprAtom::Variable(varname) if varname == MEMORY_RECALL => {
+                                        Err(anyhow!(
+                                            "Can't assign to the memory register {MEMORY_RECALL}, use M+, M- or MC"
+                                        ))
+                                    }
                                     SExprAtom::Variable(varname) => {
-                                        self.environment.insert(varname, rhs);
+                                        self.store_variable(varname, rhs, &rhs_source)?;
                                         Ok(rhs)
                                     }
-                                    _ => Err(anyhow!(
-                                        "Invalid lhs of assignment operator encountered: {at}"
+                                    SExprAtom::Number(_) | SExprAtom::Integer(_) => Err(anyhow!(
+                                        "Cannot assign to a number ({at}), only to a variable"
+                                    )),
+                                    SExprAtom::Op(_) => Err(anyhow!(
+                                        "Cannot assign to an operator ({at}), only to a variable"
                                     )),
                                 },
-                                _ => Err(anyhow!(
-                                    "Invalid lhs of assignment operator encountered: {sexpr}"
+                                SExpr::Cons(..) | SExpr::Where(..) => Err(anyhow!(
+                                    "Cannot assign to an expression {sexpr}, only to a variable"
                                 )),
                             },
                             None => Err(anyhow!("No lhs of assignment operator")),
                         }
                     }
+                    // Bitwise operators work on the operands as integers
+                    Operator::BitAnd
+                    | Operator::BitOr
+                    | Operator::ShiftLeft
+                    | Operator::ShiftRight
+                        if operands.len() == 2 =>
+                    {
+                        let (Some(rhs), Some(lhs)) = (operands.pop(), operands.pop()) else {
+                            return Err(anyhow!("Unable to extract operands of {op}"));
+                        };
+                        let lhs_value = bitwise_operand(
+                            self.interpret_sexpr(lhs)
+                                .with_context(|| format!("Failed to evaluate lhs of {op}"))?,
+                        )?;
+                        let rhs_value = bitwise_operand(
+                            self.interpret_sexpr(rhs)
+                                .with_context(|| format!("Failed to evaluate rhs of {op}"))?,
+                        )?;
+                        let res = match op {
+                            Operator::BitAnd => lhs_value & rhs_value,
+                            Operator::BitOr => lhs_value | rhs_value,
+                            _ => {
+                                let shift = u32::try_from(rhs_value)
+                                    .ok()
+                                    .filter(|shift| *shift < i64::BITS)
+                                    .ok_or(anyhow!(
+                                        "Can't shift by {rhs_value} bits, the shift must be from 0 to 63"
+                                    ))?;
+                                if op == Operator::ShiftLeft {
+                                    lhs_value << shift
+                                } else {
+                                    lhs_value >> shift
+                                }
+                            }
+                        };
+                        Ok(res as f64)
+                    }
+                    Operator::Tilde if operands.len() == 1 => {
+                        let operand_value = match operands.pop() {
+                            Some(val) => self.interpret_sexpr(val)?,
+                            None => {
+                                return Err(anyhow!("Unable to extract operand of ~"));
+                            }
+                        };
+                        Ok(!bitwise_operand(operand_value)? as f64)
+                    }
+                    // Conditionals only evaluate the branch which is taken
+                    Operator::Question if operands.len() == 3 => {
+                        let (Some(else_branch), Some(then_branch), Some(condition)) =
+                            (operands.pop(), operands.pop(), operands.pop())
+                        else {
+                            return Err(anyhow!("Unable to extract operands of conditional"));
+                        };
+                        let condition_value = self
+                            .interpret_sexpr(condition)
+                            .context("Failed to evaluate condition of conditional")?;
+                        if condition_value != 0f64 {
+                            self.interpret_sexpr(then_branch)
+                        } else {
+                            self.interpret_sexpr(else_branch)
+                        }
+                    }
+                    // Logical negation, anything nonzero is true
+                    Operator::Not if operands.len() == 1 => {
+                        let operand_value = match operands.pop() {
+                            Some(val) => self.interpret_sexpr(val)?,
+                            None => {
+                                return Err(anyhow!("Unable to extract operand of not"));
+                            }
+                        };
+                        Ok(f64::from(operand_value == 0f64))
+                    }
+                    // Percentages, written as a number followed by %
+                    Operator::Percent if operands.len() == 1 => {
+                        let operand_value = match operands.pop() {
+                            Some(val) => self.interpret_sexpr(val)?,
+                            None => {
+                                return Err(anyhow!("Unable to extract operand of percentage"));
+                            }
+                        };
+                        Ok(operand_value / 100f64)
+                    }
+                    // Augmented assignments update an existing variable
+                    Operator::PlusAssign
+                    | Operator::MinusAssign
+                    | Operator::StarAssign
+                    | Operator::SlashAssign
+                    | Operator::PercentAssign
+                    | Operator::CaretAssign
+                        if operands.len() == 2 =>
+                    {
+                        let (Some(rhs), Some(target)) = (operands.pop(), operands.pop()) else {
+                            return Err(anyhow!("Unable to extract operands of {op}"));
+                        };
+                        let varname = match target {
+                            SExpr::Atom(SExprAtom::Variable(varname)) => varname,
+                            target => {
+                                return Err(anyhow!(
+                                    "Can only use {op} on a variable, not {target}"
+                                ));
+                            }
+                        };
+                        if varname == MEMORY_RECALL {
+                            return Err(anyhow!(
+                                "Can't assign to the memory register {MEMORY_RECALL}, use M+, M- or MC"
+                            ));
+                        }
+                        if varname != ANSWER && !self.environment.contains_key(&varname) {
+                            return Err(anyhow!(
+                                "Can't use {op} on {varname}, it has no value assigned"
+                            ));
+                        }
+                        // Evaluate as the arithmetic operation on the variable, so
+                        // it behaves exactly like writing it out in full
+                        let Some(arithmetic) = op.augmented_operator() else {
+                            return Err(anyhow!("{op} is not an augmented assignment"));
+                        };
+                        let update = SExpr::Cons(
+                            SExprAtom::Op(arithmetic),
+                            vec![SExpr::Atom(SExprAtom::Variable(varname.clone())), rhs],
+                        );
+                        let source = update.to_string();
+                        let value = self
+                            .interpret_sexpr(update)
+                            .with_context(|| format!("Unable to evaluate update of {varname}"))?;
+                        self.store_variable(varname, value, &source)?;
+                        Ok(value)
+                    }
+                    // Floor and ceiling brackets
+                    Operator::LFloor | Operator::LCeil if operands.len() == 1 => {
+                        let operand_value = match operands.pop() {
+                            Some(val) => self.interpret_sexpr(val)?,
+                            None => {
+                                return Err(anyhow!("Unable to extract operand of {op}"));
+                            }
+                        };
+                        Ok(if op == Operator::LFloor {
+                            operand_value.floor()
+                        } else {
+                            operand_value.ceil()
+                        })
+                    }
                     // Finally the postfix operators
-                    '!' if operands.len() == 1 => {
-                        let lhs = match operands.pop() {
+                    Operator::Bang if operands.len() == 1 => {
+                        let operand_value = match operands.pop() {
                             Some(val) => self.interpret_sexpr(val)?,
                             None => {
                                 return Err(anyhow!("Unable to extranct operand for factorial"));
                             }
-                        } as i32;
-                        let mut res = 1;
-                        let mut iterator = lhs.abs();
-                        while iterator > 0 {
-                            res *= iterator;
-                            iterator -= 1;
+                        };
+                        // Allow for rounding error in operands which were
+                        // calculated, such as 0.1 * 30
+                        if (operand_value - operand_value.round()).abs() > FACTORIAL_TOLERANCE
+                            || operand_value.is_nan()
+                        {
+                            return Err(anyhow!(
+                                "Can't take the factorial of {operand_value}, it is not a whole number"
+                            ));
                         }
-                        if lhs < 0 {
-                            res *= -1;
+                        if operand_value < 0f64 {
+                            return Err(anyhow!(
+                                "Can't take the factorial of {operand_value}, it is negative"
+                            ));
                         }
-                        Ok(res as f64)
+                        let lhs = operand_value.round();
+                        if lhs > MAX_FACTORIAL_OPERAND as f64 {
+                            return Err(anyhow!(
+                                "factorial result exceeds representable range, {lhs}! is too large"
+                            ));
+                        }
+                        Ok((1..=lhs as u32).map(f64::from).product())
                     }
                     _ => Err(anyhow!(
                         "Encountered invalid S-expresion ({operator} {operands:?})"
                     )),
                 },
+                SExprAtom::Variable(name) => {
+                    let arguments = operands
+                        .into_iter()
+                        .map(|operand| self.interpret_sexpr(operand))
+                        .collect::<Result<Vec<f64>>>()
+                        .with_context(|| format!("Failed to evaluate argument of {name}"))?;
+                    self.call_builtin(&name, &arguments)
+                }
                 _ => Err(anyhow!(
-                    "Encountered a variable or number ({operator}) as operator in S-expression"
+                    "Encountered a number ({operator}) as operator in S-expression"
                 )),
             },
+            SExpr::Where(body, bindings) => self.interpret_where(*body, bindings),
+        }
+    }
+
+    /// Interpret the body of a where clause with its bindings made, in
+    /// order, in a temporary scope which is discarded afterwards
+    fn interpret_where(&mut self, body: SExpr, bindings: Vec<(String, SExpr)>) -> Result<f64> {
+        let mut shadowed = Vec::new();
+        let mut res = Ok(());
+        for (name, value) in bindings {
+            if name == MEMORY_RECALL || name == ANSWER {
+                res = Err(anyhow!("Can't bind {name} in a where clause"));
+                break;
+            }
+            match self.interpret_sexpr(value) {
+                Ok(value) => {
+                    let previous = self.environment.insert(name.clone(), value);
+                    shadowed.push((name, previous));
+                }
+                Err(err) => {
+                    res = Err(err.context(format!("Failed to evaluate binding of {name}")));
+                    break;
+                }
+            }
+        }
+        let res = res.and_then(|()| self.interpret_sexpr(body));
+        // Restore the shadowed values, latest binding first so a name
+        // bound twice ends up with its value from before the clause
+        for (name, previous) in shadowed.into_iter().rev() {
+            match previous {
+                Some(value) => self.environment.insert(name, value),
+                None => self.environment.remove(&name),
+            };
+        }
+        res
+    }
+}
+
+/// The fewest and most arguments the built in function name takes, the
+/// most being None if there is no limit, or None if there is no such
+/// function
+pub fn builtin_arity(name: &str) -> Option<(usize, Option<usize>)> {
+    BUILTIN_FUNCTIONS
+        .iter()
+        .find(|&&(builtin, ..)| builtin == name)
+        .map(|&(_, min_arity, max_arity)| (min_arity, max_arity))
+}
+
+/// Check a function taking from min_arity to max_arity arguments can be
+/// given the number of arguments given
+pub(super) fn check_arity(
+    name: &str,
+    min_arity: usize,
+    max_arity: Option<usize>,
+    given: usize,
+) -> Result<()> {
+    match max_arity {
+        Some(max_arity) if min_arity == max_arity && given != min_arity => Err(anyhow!(
+            "Function {name} takes {min_arity} argument{}, but was given {given}",
+            if min_arity == 1 { "" } else { "s" }
+        )),
+        Some(max_arity) if !(min_arity..=max_arity).contains(&given) => Err(anyhow!(
+            "Function {name} takes {min_arity} to {max_arity} arguments, but was given {given}"
+        )),
+        None if given < min_arity => Err(anyhow!(
+            "{name} requires at least {}",
+            if min_arity == 1 {
+                "one argument".to_string()
+            } else {
+                format!("{min_arity} arguments")
+            }
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The implementation of one of the [`BUILTIN_FUNCTIONS`], or None if
+/// there is no such function. The arguments are already checked to be
+/// within the function's arity.
+fn builtin_implementation(name: &str) -> Option<NativeFunction> {
+    let function: NativeFunction = match name {
+        "abs" => |_, arguments| Ok(arguments[0].abs()),
+        "ceil" => |_, arguments| Ok(arguments[0].ceil()),
+        "cos" => |_, arguments| Ok(arguments[0].cos()),
+        "exp" => |_, arguments| Ok(arguments[0].exp()),
+        "floor" => |_, arguments| Ok(arguments[0].floor()),
+        "ln" => |_, arguments| Ok(arguments[0].ln()),
+        // log(x) is the natural logarithm, like ln, and log(base, x) the
+        // logarithm to any base
+        "log" => |_, arguments| match *arguments {
+            [x] => Ok(x.ln()),
+            // The common bases have their own, exact, implementations
+            [10f64, x] => Ok(x.log10()),
+            [2f64, x] => Ok(x.log2()),
+            [base, x] => Ok(x.ln() / base.ln()),
+            _ => unreachable!("log takes 1 to 2 arguments"),
+        },
+        "log10" => |_, arguments| Ok(arguments[0].log10()),
+        "max" => |_, arguments| Ok(arguments.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+        "min" => |_, arguments| Ok(arguments.iter().copied().fold(f64::INFINITY, f64::min)),
+        "random" => |_, _| Ok(random()),
+        "round" => |interpreter, arguments| round(arguments, interpreter.rounding),
+        "round_half_even" => |_, arguments| round(arguments, RoundingMode::HalfEven),
+        "round_half_up" => |_, arguments| round(arguments, RoundingMode::HalfUp),
+        "sin" => |_, arguments| Ok(arguments[0].sin()),
+        "sqrt" => |_, arguments| Ok(arguments[0].sqrt()),
+        "tan" => |_, arguments| Ok(arguments[0].tan()),
+        "ulp" => |_, arguments| Ok(ulp(arguments[0])),
+        _ => return None,
+    };
+    Some(function)
+}
+
+/// Round the first argument to the number of decimal digits given by the
+/// second, or to a whole number if there isn't one, with ties broken by
+/// mode. Negative digits round to tens, hundreds and so on.
+fn round(arguments: &[f64], mode: RoundingMode) -> Result<f64> {
+    let digits = match arguments.get(1) {
+        Some(&digits) if digits.fract() != 0f64 => {
+            return Err(anyhow!(
+                "round takes a whole number of digits, got {digits}"
+            ));
         }
+        // Beyond the range of f64 either way, so the clamp changes nothing
+        Some(&digits) => digits.clamp(-1000f64, 1000f64) as i64,
+        None => 0,
+    };
+    Ok(round_to_digits(arguments[0], digits, mode))
+}
+
+/// Round value to the given number of decimal digits with ties broken by
+/// mode. The digits of value's shortest decimal form are rounded rather
+/// than value scaled by a power of ten, so 0.285 is a tie as written,
+/// rounding only happens once, and large values can't overflow.
+fn round_to_digits(value: f64, digits: i64, mode: RoundingMode) -> f64 {
+    if !value.is_finite() || value == 0f64 {
+        return value;
+    }
+    // Written as d.ddde±x, the shortest form which reads back as value
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("Scientific notation has an exponent");
+    let significand = mantissa.replace('.', "");
+    let exponent: i64 = exponent.parse().expect("The exponent is an integer");
+    // Number of significant digits kept, the last being at 10^-digits
+    let kept = exponent + 1 + digits;
+    if kept >= significand.len() as i64 {
+        return value;
     }
+    if kept < 0 {
+        // Less than a tenth of 10^-digits, so rounds to zero
+        return 0f64.copysign(value);
+    }
+    let (kept_digits, dropped_digits) = significand.split_at(kept as usize);
+    let mut rounded: u64 = if kept_digits.is_empty() {
+        0
+    } else {
+        kept_digits
+            .parse()
+            .expect("The significand is made of digits")
+    };
+    let mut dropped = dropped_digits.bytes();
+    let first_dropped = dropped.next().expect("At least one digit is dropped");
+    let round_up = match first_dropped.cmp(&b'5') {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        // Past halfway if anything follows the 5, otherwise a tie
+        Ordering::Equal => {
+            dropped.any(|digit| digit != b'0')
+                || match mode {
+                    RoundingMode::HalfUp => true,
+                    RoundingMode::HalfEven => rounded % 2 == 1,
+                }
+        }
+    };
+    if round_up {
+        rounded += 1;
+    }
+    // Reading the result back as decimal gives the nearest f64 to it
+    let rounded: f64 = format!("{rounded}e{}", -digits)
+        .parse()
+        .expect("The rounded value is a number");
+    rounded.copysign(value)
+}
+
+/// A random number from 0 up to but not including 1
+fn random() -> f64 {
+    // Each RandomState is seeded differently, so hashing nothing with a
+    // new one gives fresh random bits
+    let bits = RandomState::new().hash_one(());
+    // The top 53 bits fill the mantissa exactly
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Convert the operand of a bitwise operator to an integer, which it
+/// must already be
+fn bitwise_operand(value: f64) -> Result<i64> {
+    // i64::MAX rounds up to 2^63 as an f64, so is out of range
+    if value.fract() != 0f64 || !(i64::MIN as f64..i64::MAX as f64).contains(&value) {
+        return Err(anyhow!(
+            "bitwise operators require integer operands, got {value}"
+        ));
+    }
+    Ok(value as i64)
+}
+
+/// The unit in the last place of x, the gap between |x| and the next
+/// larger f64
+fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+    x.next_up() - x
 }
 
 #[cfg(test)]
@@ -187,6 +1198,38 @@ mod test_interpreter {
     fn test_postfix_operator() -> Result<()> {
         let mut test_interpreter = Interpreter::new();
         assert_eq!(test_interpreter.interpret("3!")?, 6f64);
+        // The largest factorial is finite, the next one is refused
+        assert!(test_interpreter.interpret("170!")?.is_finite());
+        let err = test_interpreter.interpret("171!").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "factorial result exceeds representable range, 171! is too large"
+        );
+        assert_eq!(test_interpreter.interpret("0!")?, 1f64);
+        assert_eq!(test_interpreter.interpret("5!")?, 120f64);
+        // Results past the range of 32 and 64 bit integers are exact, or
+        // as close as an f64 gets
+        assert_eq!(test_interpreter.interpret("13!")?, 6227020800f64);
+        assert_eq!(test_interpreter.interpret("21!")?, 51090942171709440000f64);
+        assert_eq!(test_interpreter.interpret("170!")?, 7.257415615307994e306);
+        assert_eq!(test_interpreter.interpret("(0.1 * 30)!")?, 6f64);
+        // Prefix minus binds more loosely, so this is -(3!)
+        assert_eq!(test_interpreter.interpret("-3!")?, -6f64);
+        let cases = [
+            (
+                "3.5!",
+                "Can't take the factorial of 3.5, it is not a whole number",
+            ),
+            ("(-2)!", "Can't take the factorial of -2, it is negative"),
+            (
+                "(0/0)!",
+                "Can't take the factorial of NaN, it is not a whole number",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = test_interpreter.interpret(input).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
         Ok(())
     }
 
@@ -197,4 +1240,754 @@ mod test_interpreter {
         assert_eq!(test_interpreter.interpret("a+4")?, 7f64);
         Ok(())
     }
+
+    #[test]
+    fn test_temporary_bindings() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("x = 10")?;
+        assert_eq!(
+            test_interpreter.interpret_with("x * y", &[("x", 2f64), ("y", 3f64)])?,
+            6f64
+        );
+        assert_eq!(test_interpreter.interpret("x")?, 10f64);
+        assert!(test_interpreter.interpret("y").is_err());
+        // Bindings are restored even if evaluation fails
+        assert!(
+            test_interpreter
+                .interpret_with("x + z", &[("x", 1f64)])
+                .is_err()
+        );
+        assert_eq!(test_interpreter.interpret("x")?, 10f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_assignment() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let err = test_interpreter.interpret("3 = 5").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot assign to a number (3), only to a variable"
+        );
+        let err = test_interpreter.interpret("(a+b) = 5").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot assign to an expression (+ a b), only to a variable"
+        );
+        // Assignments can still be used as values
+        assert_eq!(test_interpreter.interpret("3 + (x = 5)")?, 8f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unicode_variable() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("σ = 3")?, 3f64);
+        assert_eq!(test_interpreter.interpret("σ * 2")?, 6f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_metered_steps() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret_metered("3+4*2")?, (11f64, 5));
+        assert_eq!(test_interpreter.interpret_metered("-2")?, (-2f64, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_sequence() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("a=3; b=4; a*b")?, 12f64);
+        assert!(test_interpreter.interpret("c=1; undefined; c=2").is_err());
+        assert_eq!(test_interpreter.interpret("c")?, 1f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_error_recovery() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let results = test_interpreter.interpret_each("a=2; a+undefined; b=a*5; b+1");
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().ok(), Some(&2f64));
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().ok(), Some(&10f64));
+        assert_eq!(results[3].as_ref().ok(), Some(&11f64));
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_register() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("MR")?, 0f64);
+        assert_eq!(test_interpreter.memory_add(5f64), 5f64);
+        assert_eq!(test_interpreter.memory_add(-2f64), 3f64);
+        assert_eq!(test_interpreter.interpret("MR * 2")?, 6f64);
+        assert!(test_interpreter.interpret("MR = 4").is_err());
+        test_interpreter.memory_clear();
+        assert_eq!(test_interpreter.interpret("MR")?, 0f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_result() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let res = test_interpreter.interpret_full("7/2")?;
+        assert_eq!(
+            res,
+            EvalResult {
+                value: 3.5f64,
+                is_integral: false,
+                display: "3.5".to_string(),
+                suppressed: false,
+            }
+        );
+        let res = test_interpreter.interpret_full("2^10")?;
+        assert_eq!(res.value, 1024f64);
+        assert!(res.is_integral);
+        assert_eq!(res.display, "1024");
+        assert!(!test_interpreter.interpret_full("1/0")?.is_integral);
+        assert!(test_interpreter.interpret_full("x = 5;")?.suppressed);
+        assert!(!test_interpreter.interpret_full("a = 1; a + 1")?.suppressed);
+        // The display follows the format settings
+        test_interpreter.set_number_format(NumberFormat::Humane);
+        test_interpreter.set_special_values(SpecialValues::Readable);
+        assert_eq!(
+            test_interpreter.interpret_full("3.2e6")?.display,
+            "3.2 million"
+        );
+        assert_eq!(test_interpreter.interpret_full("1/0")?.display, "∞");
+        Ok(())
+    }
+
+    #[test]
+    fn test_quarantine_nonfinite() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        // Without quarantine non-finite values can be stored
+        assert!(test_interpreter.interpret("a = 0/0")?.is_nan());
+        assert!(test_interpreter.interpret("a")?.is_nan());
+
+        test_interpreter.set_quarantine_nonfinite(true);
+        let err = test_interpreter.interpret("b = 0/0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Refusing to store non-finite value NaN in b, produced by (/ 0 0)"
+        );
+        assert!(test_interpreter.interpret("b").is_err());
+        let err = test_interpreter
+            .set_variable("c", f64::INFINITY)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Refusing to store non-finite value inf in c, produced by set_variable"
+        );
+        // Non-finite intermediates are fine if the stored value is finite
+        assert_eq!(test_interpreter.interpret("d = 1/(1/0)")?, 0f64);
+        assert!(test_interpreter.interpret("1/0 * 0")?.is_nan());
+        test_interpreter.set_variable("e", 2f64)?;
+        assert_eq!(test_interpreter.interpret("e")?, 2f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_clause() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("x = 2")?;
+        assert_eq!(
+            test_interpreter.interpret("u^2 + u where u = x + 1")?,
+            12f64
+        );
+        // Later bindings can use earlier ones
+        assert_eq!(
+            test_interpreter.interpret("u * v where u = 3, v = u + 1")?,
+            12f64
+        );
+        // Locals shadow globals only inside the clause
+        assert_eq!(test_interpreter.interpret("x * 10 where x = 5")?, 50f64);
+        assert_eq!(test_interpreter.interpret("x")?, 2f64);
+        // and never leak into the global environment
+        assert!(!test_interpreter.is_defined("u"));
+        assert!(!test_interpreter.is_defined("v"));
+        // Assignments to other variables in the body are still global
+        assert_eq!(test_interpreter.interpret("y = u * 2 where u = 4")?, 8f64);
+        assert_eq!(test_interpreter.interpret("y")?, 8f64);
+        assert!(!test_interpreter.is_defined("u"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_clause_scope() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("a*b where a = 3, b = 4")?, 12f64);
+        // Evaluating a binding after the clause is an undefined variable
+        assert!(test_interpreter.interpret("a").is_err());
+        assert!(test_interpreter.interpret("b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_where_clause_errors() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("x = 2")?;
+        let err = test_interpreter
+            .interpret("u + v where u = 1, x = 7, v = w")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to evaluate binding of v");
+        // Bindings made before the error are still undone
+        assert!(!test_interpreter.is_defined("u"));
+        assert_eq!(test_interpreter.interpret("x")?, 2f64);
+        let err = test_interpreter.interpret("MR where MR = 1").unwrap_err();
+        assert_eq!(err.to_string(), "Can't bind MR in a where clause");
+        Ok(())
+    }
+
+    #[test]
+    fn test_answer() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let err = test_interpreter.interpret_full("ans + 1").unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Failed to evaluate lhs of binary operator: No previous result available for ans"
+        );
+        assert!(!test_interpreter.is_defined("ans"));
+        assert_eq!(test_interpreter.interpret_full("3 * 4")?.value, 12f64);
+        assert_eq!(test_interpreter.interpret_full("ans + 1")?.value, 13f64);
+        assert_eq!(test_interpreter.interpret_full("ans / 2")?.value, 6.5);
+        // Failed evaluations leave ans alone
+        assert!(test_interpreter.interpret_full("1 + nope").is_err());
+        assert_eq!(test_interpreter.answer(), Some(6.5));
+        let err = test_interpreter.interpret("ans = 2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't assign to ans, it always holds the previous result"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_functions() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("sqrt(16)")?, 4f64);
+        assert_eq!(test_interpreter.interpret("sin(0) + cos(0)")?, 1f64);
+        assert_eq!(test_interpreter.interpret("abs(-2.5) * 2")?, 5f64);
+        assert_eq!(test_interpreter.interpret("log10(1000)")?, 3f64);
+        assert_eq!(test_interpreter.interpret("log(e)")?, 1f64);
+        assert_eq!(test_interpreter.interpret("log(10, 1000)")?, 3f64);
+        assert_eq!(test_interpreter.interpret("log(2, 1024)")?, 10f64);
+        assert_eq!(test_interpreter.interpret("log(3, 81)")?, 4f64);
+        let err = test_interpreter.interpret("log(2, 4, 8)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Function log takes 1 to 2 arguments, but was given 3"
+        );
+        assert_eq!(test_interpreter.interpret("ln(exp(2))")?, 2f64);
+        assert_eq!(test_interpreter.interpret("tan(0)")?, 0f64);
+        assert_eq!(
+            test_interpreter.interpret("floor(-2.5) + ceil(1.2)")?,
+            -1f64
+        );
+        assert_eq!(
+            test_interpreter.interpret("round(2.5) - round(-0.4)")?,
+            3f64
+        );
+        assert_eq!(
+            test_interpreter.interpret("x = 9; sqrt(x) + sqrt(sqrt(x ^ 2))")?,
+            6f64
+        );
+
+        let err = test_interpreter.interpret("cbrt(8)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown function cbrt, the available functions are abs, ceil, cos, exp, floor, ln, log, log10, max, min, random, round, round_half_even, round_half_up, sin, sqrt, tan, ulp"
+        );
+        let err = test_interpreter.interpret("sqrt(1, 2)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Function sqrt takes 1 argument, but was given 2"
+        );
+        let err = test_interpreter.interpret("sqrt()").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Function sqrt takes 1 argument, but was given 0"
+        );
+        // Every listed builtin has an implementation
+        assert!(
+            BUILTIN_FUNCTIONS
+                .iter()
+                .all(|&(name, ..)| builtin_implementation(name).is_some())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_arity() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("max(3, 2 ^ 3, 5)")?, 8f64);
+        assert_eq!(test_interpreter.interpret("min(3, -1, 5)")?, -1f64);
+        assert_eq!(test_interpreter.interpret("max(4)")?, 4f64);
+        assert_eq!(test_interpreter.interpret("max(1,2,)")?, 2f64);
+        let err = test_interpreter.interpret("max()").unwrap_err();
+        assert_eq!(err.to_string(), "max requires at least one argument");
+        let value = test_interpreter.interpret("random()")?;
+        assert!((0f64..1f64).contains(&value));
+        let err = test_interpreter.interpret("random(1)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Function random takes 0 arguments, but was given 1"
+        );
+        assert_eq!(builtin_arity("max"), Some((1, None)));
+        assert_eq!(builtin_arity("sqrt"), Some((1, Some(1))));
+        assert_eq!(builtin_arity("cbrt"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let test_interpreter = Interpreter::new();
+        test_interpreter.check("x = 1; y = undefined / 0")?;
+        // Nothing is evaluated
+        assert!(!test_interpreter.is_defined("x"));
+        let err = test_interpreter.check("1 + 1; 2 *").unwrap_err();
+        assert_eq!(err.to_string(), "Failed to parse 2 *");
+        assert!(test_interpreter.check(" ; ").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_builtin() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.register_builtin("sum", |arguments| {
+            if arguments.is_empty() {
+                return Err(anyhow!("Function sum needs at least 1 argument"));
+            }
+            Ok(arguments.iter().sum())
+        });
+        assert_eq!(test_interpreter.interpret("sum(3, 2 ^ 3, 5)")?, 16f64);
+        let err = test_interpreter.interpret("sum()").unwrap_err();
+        assert_eq!(err.to_string(), "Function sum needs at least 1 argument");
+        // Registering an existing name replaces it
+        test_interpreter.register_builtin("sqrt", |_| Ok(0f64));
+        assert_eq!(test_interpreter.interpret("sqrt(4)")?, 0f64);
+        // A panicking function fails the call, and the session carries on
+        test_interpreter.register_builtin("first", |arguments| Ok(arguments[0]));
+        test_interpreter.interpret("x = 2")?;
+        let err = test_interpreter.interpret("first()").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Function first panicked: index out of bounds"),
+            "{err}"
+        );
+        test_interpreter.register_builtin("fail", |_| panic!("gave up"));
+        let err = test_interpreter.interpret("fail(1)").unwrap_err();
+        assert_eq!(err.to_string(), "Function fail panicked: gave up");
+        assert_eq!(test_interpreter.interpret("first(x + 1)")?, 3f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ulp() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("ulp(1.0)")?, f64::EPSILON);
+        assert_eq!(test_interpreter.interpret("ulp(0)")?, 5e-324);
+        assert_eq!(test_interpreter.interpret("ulp(-2)")?, 2f64 * f64::EPSILON);
+        assert_eq!(test_interpreter.interpret("ulp(2^53)")?, 2f64);
+        assert!(test_interpreter.interpret("ulp(1/0)")?.is_nan());
+        Ok(())
+    }
+
+    #[test]
+    fn test_radix_literals() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("0xFF + 0b1010")?, 265f64);
+        assert_eq!(test_interpreter.interpret("0o10 * 0x10 - 0b1")?, 127f64);
+        assert_eq!(test_interpreter.interpret("x = 0x10; x / 0b100")?, 4f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_modulo() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("7 % 3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("-7 % 3")?, 2f64);
+        assert_eq!(test_interpreter.interpret("7.5 % 2")?, 1.5);
+        assert_eq!(test_interpreter.interpret("-6 % 3")?, 0f64);
+        // Same precedence as * and /, grouping to the left
+        assert_eq!(test_interpreter.interpret("2 * 7 % 4")?, 2f64);
+        assert_eq!(test_interpreter.interpret("1 + 9 % 4 * 3")?, 4f64);
+        // A negative divisor keeps the sign of the dividend
+        assert_eq!(test_interpreter.interpret("7 % -3")?, 1f64);
+        // A signed divisor directly after a literal's % is still a remainder
+        assert_eq!(test_interpreter.interpret("10%-3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("10%+3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("10%~3")?, 2f64);
+        assert_eq!(test_interpreter.interpret("10%not 0")?, 0f64);
+        // The remainder of division by zero is an error rather than NaN
+        let err = test_interpreter.interpret("10 % 0").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't take the remainder of 10 divided by zero"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_augmented_assignment() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("a=5; a+=3")?, 8f64);
+        assert_eq!(test_interpreter.interpret("a")?, 8f64);
+        let cases = [
+            ("a -= 2", 6f64),
+            ("a *= 1 + 1", 12f64),
+            ("a /= 8", 1.5),
+            ("a ^= 2", 2.25),
+            ("a %= 1", 0.25),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(test_interpreter.interpret(input)?, expected, "{input}");
+        }
+        // Chained updates apply from the right
+        assert_eq!(test_interpreter.interpret("c = 1; a += c += 1")?, 2.25);
+        assert_eq!(test_interpreter.interpret("c")?, 2f64);
+
+        let err = test_interpreter.interpret("b-=1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't use -= on b, it has no value assigned"
+        );
+        let err = test_interpreter.interpret("2 += 1").unwrap_err();
+        assert_eq!(err.to_string(), "Can only use += on a variable, not 2");
+        assert!(test_interpreter.interpret("MR += 1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_floor_division() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("7 // 2")?, 3f64);
+        assert_eq!(test_interpreter.interpret("-7 // 2")?, -4f64);
+        assert_eq!(test_interpreter.interpret("7 // -2")?, -4f64);
+        assert_eq!(test_interpreter.interpret("7.5 // 2.5")?, 3f64);
+        // Same precedence as / and *, grouping to the left
+        assert_eq!(test_interpreter.interpret("6//4/2")?, 0.5);
+        assert_eq!(test_interpreter.interpret("1 + 9 // 2 * 3")?, 13f64);
+        let err = test_interpreter.interpret("1 // 0").unwrap_err();
+        assert_eq!(err.to_string(), "Can't floor divide 1 by zero");
+        Ok(())
+    }
+
+    #[test]
+    fn test_logical_operators() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("1 && 2")?, 1f64);
+        assert_eq!(test_interpreter.interpret("1 && 0")?, 0f64);
+        assert_eq!(test_interpreter.interpret("0 || 3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("0 || 0")?, 0f64);
+        assert_eq!(test_interpreter.interpret("2 < 3 && 3 < 4")?, 1f64);
+        // The rhs isn't evaluated once the lhs decides the result
+        assert_eq!(test_interpreter.interpret("0 && (1/0)")?, 0f64);
+        assert_eq!(test_interpreter.interpret("0 && undefined")?, 0f64);
+        assert_eq!(test_interpreter.interpret("1 || (x = 5)")?, 1f64);
+        assert!(!test_interpreter.is_defined("x"));
+        assert!(test_interpreter.interpret("1 && undefined").is_err());
+        // Keyword forms, with any nonzero value being true
+        assert_eq!(test_interpreter.interpret("not 0")?, 1f64);
+        assert_eq!(test_interpreter.interpret("not -2.5")?, 0f64);
+        assert_eq!(test_interpreter.interpret("not 1 or 1")?, 1f64);
+        assert_eq!(test_interpreter.interpret("3 > 2 and not 2 > 3")?, 1f64);
+        test_interpreter.interpret("y = 0")?;
+        assert_eq!(test_interpreter.interpret("y != 0 and 1 / y > 2")?, 0f64);
+        assert_eq!(test_interpreter.interpret("y == 0 or undefined")?, 1f64);
+        // The keywords are reserved
+        assert!(test_interpreter.interpret("and = 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_constants() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(
+            test_interpreter.interpret("pi * 2")?,
+            test_interpreter.interpret("tau")?
+        );
+        assert_eq!(test_interpreter.interpret("e^1")?, std::f64::consts::E);
+        assert_eq!(test_interpreter.interpret("1/0 == inf")?, 1f64);
+        assert_eq!(test_interpreter.interpret("-inf < -1e308")?, 1f64);
+        // They are ordinary variables
+        test_interpreter.interpret("pi = 3")?;
+        assert_eq!(test_interpreter.interpret("pi * 2")?, 6f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variables() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("b = 2; a = b * 3")?;
+        let names = test_interpreter
+            .variables()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<String>>();
+        assert_eq!(names, vec!["a", "b", "e", "inf", "pi", "tau"]);
+        assert_eq!(test_interpreter.variables()[0], ("a".to_string(), 6f64));
+        test_interpreter.interpret("pi = 3")?;
+        test_interpreter.reset_variables();
+        assert!(!test_interpreter.is_defined("a"));
+        assert_eq!(test_interpreter.interpret("pi")?, std::f64::consts::PI);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conditional() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("1 ? 2+3 : 1/0")?, 5f64);
+        assert_eq!(test_interpreter.interpret("0 ? 1 : 2 ? 3 : 4")?, 3f64);
+        assert_eq!(test_interpreter.interpret("x = 5; x > 3 ? x : -x")?, 5f64);
+        // Only the branch taken is evaluated
+        assert_eq!(test_interpreter.interpret("0 ? undefined : 7")?, 7f64);
+        test_interpreter.interpret("1 ? (y = 1) : (z = 2)")?;
+        assert!(test_interpreter.is_defined("y"));
+        assert!(!test_interpreter.is_defined("z"));
+        let err = test_interpreter.interpret("undefined ? 1 : 2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to evaluate condition of conditional"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitwise_operators() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("~5")?, -6f64);
+        assert_eq!(test_interpreter.interpret("1 << 10")?, 1024f64);
+        assert_eq!(test_interpreter.interpret("-16 >> 2")?, -4f64);
+        assert_eq!(test_interpreter.interpret("12 & 10")?, 8f64);
+        assert_eq!(test_interpreter.interpret("12 | 3")?, 15f64);
+        assert_eq!(test_interpreter.interpret("0xF0 | 0x0F & 0b101")?, 245f64);
+        assert_eq!(test_interpreter.interpret("x = 6; x & 3 && 1")?, 1f64);
+        let cases = [
+            (
+                "3.5 & 1",
+                "bitwise operators require integer operands, got 3.5",
+            ),
+            (
+                "~(2 ^ 63)",
+                "bitwise operators require integer operands, got 9223372036854776000",
+            ),
+            (
+                "~(1 / 0)",
+                "bitwise operators require integer operands, got inf",
+            ),
+            (
+                "1 << 64",
+                "Can't shift by 64 bits, the shift must be from 0 to 63",
+            ),
+            (
+                "1 >> -1",
+                "Can't shift by -1 bits, the shift must be from 0 to 63",
+            ),
+        ];
+        for (input, expected) in cases {
+            let err = test_interpreter.interpret(input).unwrap_err();
+            assert_eq!(err.to_string(), expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_percent_suffix() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("growth = 3.5%")?, 0.035);
+        assert_eq!(test_interpreter.interpret("error = 2‰")?, 0.002);
+        assert_eq!(test_interpreter.interpret("revenue = 200")?, 200f64);
+        assert_eq!(test_interpreter.interpret("50% * revenue")?, 100f64);
+        assert_eq!(test_interpreter.interpret("revenue * (1 + 25%)")?, 250f64);
+        // Between two values % is still the remainder
+        assert_eq!(test_interpreter.interpret("10%3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("revenue%7")?, 4f64);
+        assert_eq!(test_interpreter.interpret("10%-3")?, 1f64);
+        // but a spaced out sign is a binary operator after a percentage
+        assert_eq!(test_interpreter.interpret("10% - 3")?, -2.9);
+        assert!(test_interpreter.interpret("revenue%").is_err());
+        // Added or subtracted percentages are relative to the lhs
+        assert_eq!(test_interpreter.interpret("200 + 10%")?, 220f64);
+        assert_eq!(test_interpreter.interpret("200 - 10%")?, 180f64);
+        assert_eq!(test_interpreter.interpret("revenue - 25% + 50%")?, 225f64);
+        // but only when the percentage is the whole rhs
+        assert_eq!(test_interpreter.interpret("200 + 10% * 2")?, 200.2);
+        assert_eq!(test_interpreter.interpret("200 * 10%")?, 20f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_comparisons() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("3 < 5")?, 1f64);
+        assert_eq!(test_interpreter.interpret("5 == 5")?, 1f64);
+        assert_eq!(test_interpreter.interpret("2 >= 3")?, 0f64);
+        assert_eq!(test_interpreter.interpret("2 > 3")?, 0f64);
+        assert_eq!(test_interpreter.interpret("3 <= 3")?, 1f64);
+        assert_eq!(test_interpreter.interpret("1 != 2")?, 1f64);
+        // Arithmetic happens before comparing, assignment after
+        assert_eq!(test_interpreter.interpret("x = 1 + 1 == 2")?, 1f64);
+        assert_eq!(test_interpreter.interpret("x")?, 1f64);
+        // NaN is unequal to everything, itself included
+        assert_eq!(test_interpreter.interpret("0/0 == 0/0")?, 0f64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_error() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.last_error(), None);
+        let err = test_interpreter.interpret("2 * ans").unwrap_err();
+        assert_eq!(
+            test_interpreter.last_error(),
+            Some(format!("{err:#}").as_str())
+        );
+        assert_eq!(
+            test_interpreter.last_error(),
+            Some("Failed to evaluate rhs of binary operator: No previous result available for ans")
+        );
+        test_interpreter.interpret("2 * 3")?;
+        assert_eq!(test_interpreter.last_error(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_parse_tree() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert!(test_interpreter.last_parse_tree().is_none());
+        test_interpreter.interpret("2+3")?;
+        assert_eq!(
+            test_interpreter.last_parse_tree().map(SExpr::to_string),
+            Some("(+ 2 3)".to_string())
+        );
+        // Only the last statement is kept
+        test_interpreter.interpret("x = 2; x * 4")?;
+        assert_eq!(
+            test_interpreter.last_parse_tree().map(SExpr::to_string),
+            Some("(* x 4)".to_string())
+        );
+        // A tree which fails to evaluate is still kept, one which doesn't
+        // parse leaves none
+        assert!(test_interpreter.interpret("y - 1").is_err());
+        assert_eq!(
+            test_interpreter.last_parse_tree().map(SExpr::to_string),
+            Some("(- y 1)".to_string())
+        );
+        assert!(test_interpreter.interpret("3 +").is_err());
+        assert!(test_interpreter.last_parse_tree().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_floor_ceiling() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("⌊2.7⌋")?, 2f64);
+        assert_eq!(test_interpreter.interpret("⌈2.1⌉")?, 3f64);
+        assert_eq!(test_interpreter.interpret("⌊-2.5⌋")?, -3f64);
+        assert_eq!(test_interpreter.interpret("⌈-2.5⌉")?, -2f64);
+        assert_eq!(test_interpreter.interpret("2 * ⌊7 / 2⌋ + ⌈0.2⌉")?, 7f64);
+        assert_eq!(test_interpreter.interpret("⌊⌈1.5⌉ / 4⌋")?, 0f64);
+        assert!(test_interpreter.interpret("⌊2.7").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_assignment_style() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.set_assignment_style(AssignmentStyle::ColonEquals);
+        assert_eq!(test_interpreter.interpret("x := 5")?, 5f64);
+        assert_eq!(test_interpreter.interpret("x = 5")?, 1f64);
+        assert_eq!(test_interpreter.interpret("x = 4")?, 0f64);
+        assert_eq!(test_interpreter.interpret("x")?, 5f64);
+        assert_eq!(test_interpreter.interpret("y := x = 5 ? 2 : 3")?, 2f64);
+        test_interpreter.set_assignment_style(AssignmentStyle::Equals);
+        assert_eq!(test_interpreter.interpret("x = 4")?, 4f64);
+        assert!(test_interpreter.interpret("x := 3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rounding() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let cases = [
+            // Rounded as written, where scaling by 100 first gives 28.499...
+            ("round(0.285, 2)", 0.29),
+            ("round_half_even(0.285, 2)", 0.28),
+            ("round(1.005, 2)", 1.01),
+            ("round(2.5)", 3f64),
+            ("round(3.5)", 4f64),
+            ("round(-2.5)", -3f64),
+            ("round_half_even(2.5)", 2f64),
+            ("round_half_even(3.5)", 4f64),
+            ("round_half_even(-2.5)", -2f64),
+            ("round_half_up(2.5)", 3f64),
+            ("round_half_up(3.5)", 4f64),
+            ("round_half_even(2.51)", 3f64),
+            ("round(12.34567, 3)", 12.346),
+            ("round(9.996, 2)", 10f64),
+            ("round(0.4)", 0f64),
+            ("round(0.0004, 2)", 0f64),
+            // Negative digits round to tens, hundreds and so on
+            ("round(1234.5, -2)", 1200f64),
+            ("round(1250, -2)", 1300f64),
+            ("round_half_even(1250, -2)", 1200f64),
+            ("round(-1750, -3)", -2000f64),
+            // Scaling these by a power of ten overflows or underflows
+            ("round(1.5e300, 10)", 1.5e300),
+            ("round(1.5e300, -300)", 2e300),
+            ("round_half_even(2.5e300, -300)", 2e300),
+            ("round(1.7976931348623157e308, 2)", f64::MAX),
+            ("round(2.5e-300, 300)", 3e-300),
+            ("round_half_even(2.5e-300, 300)", 2e-300),
+            ("round(123.456, 400)", 123.456),
+            ("round(123.456, -400)", 0f64),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(test_interpreter.interpret(input)?, expected, "{input}");
+        }
+        assert!(test_interpreter.interpret("round(1/0, 2)")?.is_infinite());
+        let err = test_interpreter.interpret("round(1.5, 0.5)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "round takes a whole number of digits, got 0.5"
+        );
+
+        // Bare round follows the interpreter's rounding mode
+        assert_eq!(test_interpreter.rounding(), RoundingMode::HalfUp);
+        test_interpreter.set_rounding(RoundingMode::HalfEven);
+        assert_eq!(test_interpreter.interpret("round(2.5)")?, 2f64);
+        assert_eq!(test_interpreter.interpret("round(0.285, 2)")?, 0.28);
+        assert_eq!(test_interpreter.interpret("round_half_up(2.5)")?, 3f64);
+        assert_eq!(
+            RoundingMode::from_name("half_even")?,
+            RoundingMode::HalfEven
+        );
+        assert!(RoundingMode::from_name("down").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_division_mode() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        assert_eq!(test_interpreter.interpret("7/2")?, 3.5);
+        test_interpreter.set_division_mode(DivisionMode::Integer);
+        assert_eq!(test_interpreter.interpret("7/2")?, 3f64);
+        assert_eq!(test_interpreter.interpret("-7/2")?, -3f64);
+        assert_eq!(test_interpreter.interpret("7.5/2")?, 3.75);
+        assert_eq!(test_interpreter.interpret("7/2.5")?, 2.8);
+        // Only the values of the operands matter, not how they are written
+        assert_eq!(test_interpreter.interpret("7.0/2")?, 3f64);
+        assert_eq!(test_interpreter.interpret("-7.0/2")?, -3f64);
+        assert_eq!(test_interpreter.interpret("(7.0)/2")?, 3f64);
+        assert_eq!(test_interpreter.interpret("7/2e0")?, 3f64);
+        assert_eq!(test_interpreter.interpret("x = 7.0; x/2")?, 3f64);
+        assert_eq!(test_interpreter.interpret("(3 + 3)/4")?, 1f64);
+        // Whole results of earlier divisions stay whole
+        assert_eq!(test_interpreter.interpret("(7/2)/2")?, 1f64);
+        assert_eq!(test_interpreter.interpret("1/0")?, f64::INFINITY);
+        Ok(())
+    }
 }
@@ -8,10 +8,12 @@ use anyhow::{Context, Result, anyhow};
 // Local Crate Uses
 
 /// A single token being parsed
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum Token {
-    Op(char),
+pub enum Token {
+    Op(Operator),
     Atom(AtomType),
+    Keyword(Keyword),
     EOF,
 }
 
@@ -21,42 +23,316 @@ impl fmt::Display for Token {
             Token::Op(c) => write!(f, "{}", c),
             Token::Atom(at) => match at {
                 AtomType::Number(n) => write!(f, "{}", n),
+                AtomType::Integer(n) => write!(f, "{}", n),
                 AtomType::Variable(varname) => write!(f, "{}", varname),
+                AtomType::Percent(n) => write!(f, "{}%", n),
             },
+            Token::Keyword(keyword) => write!(f, "{}", keyword),
             Token::EOF => write!(f, "EOF"),
         }
     }
 }
 
 impl Token {
-    /// Create a new Token representing an operation
-    fn new_op(operator: char) -> Result<Self> {
-        Ok(Self::Op(operator))
-    }
-
     /// Create a new Token representing a number
     fn new_number(num: &str) -> Result<Self> {
         Ok(Token::Atom(AtomType::new_num(num)?))
     }
 
-    /// Create a new Token representing a variable
+    /// Create a new Token representing a variable, or a keyword if the
+    /// name is reserved
     fn new_variable(var_name: &str) -> Result<Self> {
+        if let Some(keyword) = Keyword::from_name(var_name) {
+            return Ok(Token::Keyword(keyword));
+        }
         Ok(Token::Atom(AtomType::new_variable(var_name)?))
     }
 }
 
+/// The operators and punctuation which can appear in the input
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operator {
+    /// `+`, addition or prefix plus
+    Plus,
+    /// `-`, subtraction or negation
+    Minus,
+    /// `*`, multiplication
+    Star,
+    /// `/`, division
+    Slash,
+    /// `//`, floor division
+    DoubleSlash,
+    /// `%`, modulo
+    Percent,
+    /// `^`, exponentiation
+    Caret,
+    /// `!`, factorial
+    Bang,
+    /// `=`, assignment
+    Assign,
+    /// `+=`, adding to a variable
+    PlusAssign,
+    /// `-=`, subtracting from a variable
+    MinusAssign,
+    /// `*=`, multiplying a variable
+    StarAssign,
+    /// `/=`, dividing a variable
+    SlashAssign,
+    /// `%=`, taking the remainder of a variable
+    PercentAssign,
+    /// `^=`, raising a variable to a power
+    CaretAssign,
+    /// `:=`, assigning to a variable in the Pascal assignment style
+    ColonAssign,
+    /// `==`, equal to
+    Equal,
+    /// `!=`, not equal to
+    NotEqual,
+    /// `<`, less than
+    Less,
+    /// `<=`, less than or equal to
+    LessEqual,
+    /// `>`, greater than
+    Greater,
+    /// `>=`, greater than or equal to
+    GreaterEqual,
+    /// `&&`, logical and
+    And,
+    /// `||`, logical or
+    Or,
+    /// `not`, logical negation, written as the keyword
+    Not,
+    /// `&`, bitwise and
+    BitAnd,
+    /// `|`, bitwise or
+    BitOr,
+    /// `<<`, shifting bits left
+    ShiftLeft,
+    /// `>>`, shifting bits right
+    ShiftRight,
+    /// `~`, bitwise not
+    Tilde,
+    /// `?`, starting the branches of a conditional
+    Question,
+    /// `:`, separating the branches of a conditional
+    Colon,
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+    /// `,`, separating arguments and where bindings
+    Comma,
+    /// `⌊`, opening a floor
+    LFloor,
+    /// `⌋`, closing a floor
+    RFloor,
+    /// `⌈`, opening a ceiling
+    LCeil,
+    /// `⌉`, closing a ceiling
+    RCeil,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl Operator {
+    /// Every operator, in the order of the variants
+    pub const ALL: &[Operator] = &[
+        Operator::Plus,
+        Operator::Minus,
+        Operator::Star,
+        Operator::Slash,
+        Operator::DoubleSlash,
+        Operator::Percent,
+        Operator::Caret,
+        Operator::Bang,
+        Operator::Assign,
+        Operator::PlusAssign,
+        Operator::MinusAssign,
+        Operator::StarAssign,
+        Operator::SlashAssign,
+        Operator::PercentAssign,
+        Operator::CaretAssign,
+        Operator::ColonAssign,
+        Operator::Equal,
+        Operator::NotEqual,
+        Operator::Less,
+        Operator::LessEqual,
+        Operator::Greater,
+        Operator::GreaterEqual,
+        Operator::And,
+        Operator::Or,
+        Operator::Not,
+        Operator::BitAnd,
+        Operator::BitOr,
+        Operator::ShiftLeft,
+        Operator::ShiftRight,
+        Operator::Tilde,
+        Operator::Question,
+        Operator::Colon,
+        Operator::LParen,
+        Operator::RParen,
+        Operator::Comma,
+        Operator::LFloor,
+        Operator::RFloor,
+        Operator::LCeil,
+        Operator::RCeil,
+    ];
+
+    /// The text of the operator as it is written in the input
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Star => "*",
+            Operator::Slash => "/",
+            Operator::DoubleSlash => "//",
+            Operator::Percent => "%",
+            Operator::Caret => "^",
+            Operator::Bang => "!",
+            Operator::Assign => "=",
+            Operator::PlusAssign => "+=",
+            Operator::MinusAssign => "-=",
+            Operator::StarAssign => "*=",
+            Operator::SlashAssign => "/=",
+            Operator::PercentAssign => "%=",
+            Operator::CaretAssign => "^=",
+            Operator::ColonAssign => ":=",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+            Operator::And => "&&",
+            Operator::Or => "||",
+            Operator::Not => "not",
+            Operator::BitAnd => "&",
+            Operator::BitOr => "|",
+            Operator::ShiftLeft => "<<",
+            Operator::ShiftRight => ">>",
+            Operator::Tilde => "~",
+            Operator::Question => "?",
+            Operator::Colon => ":",
+            Operator::LParen => "(",
+            Operator::RParen => ")",
+            Operator::Comma => ",",
+            Operator::LFloor => "⌊",
+            Operator::RFloor => "⌋",
+            Operator::LCeil => "⌈",
+            Operator::RCeil => "⌉",
+        }
+    }
+
+    /// The arithmetic operator applied by an augmented assignment, such as
+    /// `+` for `+=`, or None if this isn't an augmented assignment
+    pub fn augmented_operator(self) -> Option<Operator> {
+        match self {
+            Operator::PlusAssign => Some(Operator::Plus),
+            Operator::MinusAssign => Some(Operator::Minus),
+            Operator::StarAssign => Some(Operator::Star),
+            Operator::SlashAssign => Some(Operator::Slash),
+            Operator::PercentAssign => Some(Operator::Percent),
+            Operator::CaretAssign => Some(Operator::Caret),
+            _ => None,
+        }
+    }
+
+    /// Check whether the operator assigns to the variable on its left,
+    /// either `=` or an augmented assignment
+    pub fn is_assignment(self) -> bool {
+        self == Operator::Assign || self.augmented_operator().is_some()
+    }
+
+    /// Look up the operator written as symbol
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|op| op.symbol() == symbol)
+    }
+
+    /// The keyword the operator is written as, if it is a word rather
+    /// than a symbol
+    pub fn keyword(self) -> Option<Keyword> {
+        Keyword::from_name(self.symbol())
+    }
+}
+
+/// Reserved words, which can't be used as variable names
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keyword {
+    /// Introduces local bindings, as in `u^2 + u where u = x + 1`
+    Where,
+    /// Logical and, the same as `&&`
+    And,
+    /// Logical or, the same as `||`
+    Or,
+    /// Logical negation, as in `not x == 1`
+    Not,
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Keyword::Where => write!(f, "where"),
+            Keyword::And => write!(f, "and"),
+            Keyword::Or => write!(f, "or"),
+            Keyword::Not => write!(f, "not"),
+        }
+    }
+}
+
+impl Keyword {
+    /// Every keyword
+    pub const ALL: &[Keyword] = &[Keyword::Where, Keyword::And, Keyword::Or, Keyword::Not];
+
+    /// Look up the keyword with the given name
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "where" => Some(Keyword::Where),
+            "and" => Some(Keyword::And),
+            "or" => Some(Keyword::Or),
+            "not" => Some(Keyword::Not),
+            _ => None,
+        }
+    }
+
+    /// The operator the keyword stands for, if it is one
+    pub fn operator(self) -> Option<Operator> {
+        match self {
+            Keyword::Where => None,
+            Keyword::And => Some(Operator::And),
+            Keyword::Or => Some(Operator::Or),
+            Keyword::Not => Some(Operator::Not),
+        }
+    }
+}
+
 /// The possible types of an Atom
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum AtomType {
+pub enum AtomType {
     /// A single floating point number
     Number(f64),
+    /// A whole number, written without a decimal point or exponent
+    Integer(i64),
     /// A variable identifier
     Variable(String),
+    /// A number followed by a percent sign, holding the number as written
+    /// (so `10%` holds 10)
+    Percent(f64),
 }
 
 impl AtomType {
-    /// Create a new number Atom
+    /// Create a new number Atom, an integer if the literal has no decimal
+    /// point or exponent and fits in an i64
     fn new_num(num: &str) -> Result<Self> {
+        if !num.contains(['.', 'e', 'E'])
+            && let Ok(integer) = num.parse::<i64>()
+        {
+            return Ok(AtomType::Integer(integer));
+        }
         let internal_num = num.parse::<f64>().context("Failed to parse number")?;
         Ok(AtomType::Number(internal_num))
     }
@@ -68,7 +344,7 @@ impl AtomType {
 }
 
 /// Lexes a string into a sequence of Tokens
-pub(crate) struct Lexer {
+pub struct Lexer {
     /// The generated sequence of tokens
     tokens: Vec<Token>,
     /// The input being Lexed
@@ -82,7 +358,7 @@ pub(crate) struct Lexer {
 // Create Lexer
 impl Lexer {
     /// Create a new lexer
-    pub(crate) fn new(input: &str) -> Result<Self> {
+    pub fn new(input: &str) -> Result<Self> {
         let input_vec = input.trim().to_string().chars().collect::<Vec<char>>();
         Ok(Self {
             tokens: Vec::new(),
@@ -96,20 +372,23 @@ impl Lexer {
 // Main lexer functions
 impl Lexer {
     /// Lex the input into a series of Tokens
-    pub(crate) fn lex(&mut self) -> Result<Vec<Token>> {
+    pub fn lex(&mut self) -> Result<Vec<Token>> {
         while !self.at_end() {
             self.start_position = self.current_position;
             let cur_char = self
                 .pop()
                 .context("Failed to get next character during lexing")?;
+            // Match the longest operator starting here, so that an operator
+            // is never split into shorter ones
+            if let Some(op) = self.match_operator() {
+                self.current_position = self.start_position + op.symbol().chars().count();
+                self.tokens.push(Token::Op(op));
+                continue;
+            }
             match cur_char {
-                // Match all the operators
-                '(' | ')' | '*' | '/' | '+' | '-' | '^' | '!' | '=' => self.tokens.push(
-                    Token::new_op(cur_char)
-                        .context("Unable to create new operator token during lexing")?,
-                ),
-                // Match possible starts of variable names
-                'a'..='z' | 'A'..='Z' | '_' => {
+                // Match possible starts of variable names, any (unicode)
+                // letter or an underscore
+                c if c.is_alphabetic() || c == '_' => {
                     self.consume_variable()?;
                     let new_var_name =
                         match self.input.get(self.start_position..self.current_position) {
@@ -125,6 +404,13 @@ impl Lexer {
                             .context("Unable to create new variable from consumed variable")?,
                     );
                 }
+                // Match a hexadecimal, octal, or binary integer literal
+                '0' if !self.at_end()
+                    && matches!(self.peek()?, 'x' | 'X' | 'o' | 'O' | 'b' | 'B') =>
+                {
+                    let atom = self.consume_radix_number()?;
+                    self.tokens.push(Token::Atom(atom));
+                }
                 // Match the start of a number
                 '0'..='9' => {
                     self.consume_number()?;
@@ -137,10 +423,26 @@ impl Lexer {
                                 ));
                             }
                         };
-                    self.tokens.push(
-                        Token::new_number(&new_num)
+                    // Digit separators are only for readability
+                    let new_num = new_num.replace('_', "");
+                    let token = match self.consume_scale_suffix()? {
+                        Some(suffix) => {
+                            let value = new_num.parse::<f64>().context("Failed to parse number")?;
+                            match suffix {
+                                '%' => Token::Atom(AtomType::Percent(value)),
+                                _ => Token::Atom(AtomType::Number(value / 1000f64)),
+                            }
+                        }
+                        None => Token::new_number(&new_num)
                             .context("Unable to create new number token from consumed number")?,
-                    );
+                    };
+                    self.tokens.push(token);
+                }
+                // Permille is only a suffix of number literals
+                '‰' => {
+                    return Err(anyhow!(
+                        "The permille sign ‰ must directly follow a number, as in 2‰"
+                    ));
                 }
                 // Match spaces (and other whitespace)
                 c if c.is_whitespace() => {}
@@ -158,6 +460,22 @@ impl Lexer {
         Ok(take(&mut self.tokens))
     }
 
+    /// Find the longest operator starting at the start of the current
+    /// token, if there is one
+    fn match_operator(&self) -> Option<Operator> {
+        let rest = &self.input[self.start_position..];
+        Operator::ALL
+            .iter()
+            .copied()
+            // Operators written as words are lexed as keywords
+            .filter(|op| op.keyword().is_none())
+            .filter(|op| {
+                let symbol = op.symbol().chars().collect::<Vec<char>>();
+                rest.starts_with(&symbol)
+            })
+            .max_by_key(|op| op.symbol().chars().count())
+    }
+
     /// Increment current position until it is past the end of the variable
     fn consume_variable(&mut self) -> Result<()> {
         while !self.at_end() && self.is_valid_var().context("Failed to consume variable")? {
@@ -168,8 +486,19 @@ impl Lexer {
     }
 
     /// Increment current position until it is past the end of a number
+    ///
+    /// The accepted grammar for number literals is
+    /// `digit+ ( '.' digit+ )? ( ('e' | 'E') ('+' | '-')? digit+ )?`, so
+    /// `3`, `3.14`, `007` (leading zeros are allowed and ignored, `007` is
+    /// 7) and `2.5e-3` are all valid, while `1.`, `1.2.3`, `1e+`, `2e3.5`,
+    /// or a number running straight into a letter such as `2x` are
+    /// rejected with an error naming the literal. Literals can't start
+    /// with a `.`, so `.5` is not a number. Single underscores may separate
+    /// digits anywhere in the literal, as in `1_000_000` or `1e1_0`, or
+    /// come just before the decimal point, as in `1_.5`, but never end it.
     fn consume_number(&mut self) -> Result<()> {
         let mut encounted_decimal = false;
+        let mut encountered_exponent = false;
 
         while !self.at_end() {
             let cur_char = self.peek()?;
@@ -177,14 +506,66 @@ impl Lexer {
                 '0'..='9' => {
                     self.consume();
                 }
+                '.' if encountered_exponent => {
+                    self.consume();
+                    return Err(anyhow!(
+                        "Malformed number literal {}, the exponent must be a whole number",
+                        self.current_lexeme()
+                    ));
+                }
+                '_' => {
+                    self.consume();
+                    // Separators go between digits, or before the decimal
+                    // point as in `1_000_.5`, so `1__000` and `1_` are
+                    // rejected
+                    let before_decimal = !encounted_decimal && !encountered_exponent;
+                    if self.at_end()
+                        || !(self.peek()?.is_ascii_digit()
+                            || (before_decimal && self.peek()? == '.'))
+                    {
+                        return Err(anyhow!(
+                            "Malformed number literal {}, underscore separators must be between digits",
+                            self.current_lexeme()
+                        ));
+                    }
+                }
+                'e' | 'E' if !encountered_exponent => {
+                    encountered_exponent = true;
+                    self.consume();
+                    if !self.at_end() && matches!(self.peek()?, '+' | '-') {
+                        self.consume();
+                    }
+                    // The exponent must have at least one digit
+                    if self.at_end() || !self.peek()?.is_ascii_digit() {
+                        return Err(anyhow!(
+                            "Malformed number literal {}, expected digits in the exponent",
+                            self.current_lexeme()
+                        ));
+                    }
+                }
                 '.' => {
                     if encounted_decimal {
                         return Err(anyhow!(
-                            "Encountered two decimal points in single number during lexing"
+                            "Encountered two decimal points in number literal {}. during lexing",
+                            self.current_lexeme()
                         ));
                     }
                     encounted_decimal = true;
                     self.consume();
+                    // A decimal point must be followed by at least one digit
+                    if self.at_end() || !self.peek()?.is_ascii_digit() {
+                        return Err(anyhow!(
+                            "Incomplete number literal {}, expected digits after the decimal point",
+                            self.current_lexeme()
+                        ));
+                    }
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    self.consume();
+                    return Err(anyhow!(
+                        "Malformed number literal {}, a number can't be directly followed by {c}",
+                        self.current_lexeme()
+                    ));
                 }
                 _ => {
                     break;
@@ -196,12 +577,103 @@ impl Lexer {
     }
 }
 
+// Percent and permille suffixes
+impl Lexer {
+    /// Consume a `%` or `‰` directly following a number literal, returning
+    /// the suffix, or None if there is no suffix
+    ///
+    /// `‰` always divides the number by 1000. A `%` is only a suffix, making
+    /// a percentage, when no operand follows it, so `3.5% * x` is 0.035
+    /// times x while `10%3` (like `10 % 3`) is still a remainder. A prefix
+    /// operator directly after the `%` starts an operand too, so `10%-3`
+    /// is the remainder of 10 divided by -3, but `10% - 3` subtracts.
+    fn consume_scale_suffix(&mut self) -> Result<Option<char>> {
+        if self.at_end() {
+            return Ok(None);
+        }
+        let suffix = match self.peek()? {
+            '‰' => '‰',
+            '%' if !self.operand_follows(self.current_position + 1) => '%',
+            _ => return Ok(None),
+        };
+        self.consume();
+        Ok(Some(suffix))
+    }
+
+    /// Check whether the next character from position on, ignoring
+    /// whitespace, starts an operand
+    ///
+    /// The prefix operators `+`, `-` and `~` only count when they come
+    /// directly at position, since after whitespace they read as binary
+    /// operators.
+    fn operand_follows(&self, position: usize) -> bool {
+        if self
+            .input
+            .get(position)
+            .is_some_and(|c| matches!(c, '+' | '-' | '~'))
+        {
+            return true;
+        }
+        self.input[position..]
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '(' | '⌊' | '⌈'))
+    }
+}
+
+// Radix prefixed integer literals
+impl Lexer {
+    /// Consume an integer literal written in another base, after its
+    /// leading `0`, returning its value
+    ///
+    /// The base is given by the prefix, `0x` for hexadecimal, `0o` for
+    /// octal and `0b` for binary (in either case), and must be followed by
+    /// at least one digit of that base, so `0xFF` is 255 and `0b101` is 5.
+    /// Values too large for an i64 become floating point numbers.
+    fn consume_radix_number(&mut self) -> Result<AtomType> {
+        let (radix, base_name) = match self.pop()? {
+            'x' | 'X' => (16, "hexadecimal"),
+            'o' | 'O' => (8, "octal"),
+            _ => (2, "binary"),
+        };
+        // Take the whole alphanumeric run, so invalid digits are reported
+        // as part of the literal rather than lexed as a variable
+        while !self.at_end() && (self.peek()?.is_alphanumeric() || self.peek()? == '_') {
+            self.consume();
+        }
+        let lexeme = self.current_lexeme();
+        if lexeme[2..].starts_with('_') || lexeme.ends_with('_') || lexeme.contains("__") {
+            return Err(anyhow!(
+                "Malformed number literal {lexeme}, underscore separators must be between digits"
+            ));
+        }
+        let digits = lexeme[2..].replace('_', "");
+        if digits.is_empty() {
+            return Err(anyhow!(
+                "Incomplete number literal {lexeme}, expected {base_name} digits after {}",
+                &lexeme[..2]
+            ));
+        }
+        if let Some(invalid) = digits.chars().find(|c| !c.is_digit(radix)) {
+            return Err(anyhow!(
+                "Invalid digit {invalid} in {base_name} literal {lexeme}"
+            ));
+        }
+        let value = u64::from_str_radix(&digits, radix)
+            .map_err(|_| anyhow!("The {base_name} literal {lexeme} is too large"))?;
+        Ok(match i64::try_from(value) {
+            Ok(integer) => AtomType::Integer(integer),
+            Err(_) => AtomType::Number(value as f64),
+        })
+    }
+}
+
 // Some utility methods for the lexer
 impl Lexer {
     /// Return the next character without consuming it
     fn peek(&self) -> Result<char> {
         if let Some(c) = self.input.get(self.current_position) {
-            return Ok(c.clone());
+            return Ok(*c);
         }
         Err(anyhow!("Tried to index past end of input during lexing"))
     }
@@ -214,10 +686,17 @@ impl Lexer {
     }
 
     /// Consume the next character, not returning it
-    fn consume(&mut self) -> () {
+    fn consume(&mut self) {
         self.current_position += 1;
     }
 
+    /// Get the text of the token currently being lexed
+    fn current_lexeme(&self) -> String {
+        self.input[self.start_position..self.current_position]
+            .iter()
+            .collect()
+    }
+
     /// Determine if entire input has been parsed
     fn at_end(&self) -> bool {
         self.current_position >= self.input.len()
@@ -256,6 +735,7 @@ impl Lexer {
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod lexer_tests {
     use super::*;
 
@@ -266,7 +746,7 @@ mod lexer_tests {
         // Run the lexer
         let lexed_tokens = test_lexer.lex()?;
         // Test that the token created is correct
-        let test_token = match lexed_tokens.get(0) {
+        let test_token = match lexed_tokens.first() {
             Some(t) => t,
             None => {
                 return Err(anyhow!("Lexing returned an empty vector"));
@@ -294,7 +774,7 @@ mod lexer_tests {
         // Run the lexer
         let lexed_tokens = test_lexer.lex()?;
         // Test that the token created is correct
-        let test_token = match lexed_tokens.get(0) {
+        let test_token = match lexed_tokens.first() {
             Some(t) => t,
             None => {
                 return Err(anyhow!("Lexing returned an empty vector"));
@@ -320,7 +800,7 @@ mod lexer_tests {
         // Run the lexer
         let lexed_tokens = test_lexer.lex()?;
         // Test that the token created is correct
-        let test_token = match lexed_tokens.get(0) {
+        let test_token = match lexed_tokens.first() {
             Some(t) => t,
             None => {
                 return Err(anyhow!("Lexing returned an empty vector"));
@@ -329,7 +809,7 @@ mod lexer_tests {
 
         match test_token {
             Token::Op(operator) => {
-                assert_eq!(operator, &'+');
+                assert_eq!(operator, &Operator::Plus);
             }
             _ => return Err(anyhow!("Lexer returned incorrect token type")),
         }
@@ -337,6 +817,46 @@ mod lexer_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_lex_every_operator() -> Result<()> {
+        for op in Operator::ALL {
+            assert_eq!(Operator::from_symbol(op.symbol()), Some(*op));
+            let token = match op.keyword() {
+                Some(keyword) => Token::Keyword(keyword),
+                None => Token::Op(*op),
+            };
+            assert_eq!(Lexer::new(op.symbol())?.lex()?, vec![token, Token::EOF]);
+        }
+        // Adjacent operators are separate tokens
+        assert_eq!(
+            Lexer::new("-(⌊")?.lex()?,
+            vec![
+                Token::Op(Operator::Minus),
+                Token::Op(Operator::LParen),
+                Token::Op(Operator::LFloor),
+                Token::EOF
+            ]
+        );
+        // The longest operator is taken, so <= isn't < followed by =
+        assert_eq!(
+            Lexer::new("a<=b==c!=d<e")?.lex()?,
+            vec![
+                Token::Atom(AtomType::Variable("a".to_string())),
+                Token::Op(Operator::LessEqual),
+                Token::Atom(AtomType::Variable("b".to_string())),
+                Token::Op(Operator::Equal),
+                Token::Atom(AtomType::Variable("c".to_string())),
+                Token::Op(Operator::NotEqual),
+                Token::Atom(AtomType::Variable("d".to_string())),
+                Token::Op(Operator::Less),
+                Token::Atom(AtomType::Variable("e".to_string())),
+                Token::EOF
+            ]
+        );
+        assert_eq!(Operator::from_symbol("**"), None);
+        Ok(())
+    }
+
     #[test]
     fn test_lex_series() -> Result<()> {
         // Create the test lexer
@@ -345,14 +865,14 @@ mod lexer_tests {
         let lexed_tokens = test_lexer.lex()?;
         // Create a vec of the expected output
         let expected_tokens: Vec<Token> = vec![
-            Token::Op('('),
+            Token::Op(Operator::LParen),
             Token::Atom(AtomType::Number(3.14)),
-            Token::Op(')'),
-            Token::Op('*'),
-            Token::Atom(AtomType::Number(5f64)),
-            Token::Op('+'),
+            Token::Op(Operator::RParen),
+            Token::Op(Operator::Star),
+            Token::Atom(AtomType::Integer(5)),
+            Token::Op(Operator::Plus),
             Token::Atom(AtomType::Variable("a".to_string())),
-            Token::Op('/'),
+            Token::Op(Operator::Slash),
             Token::Atom(AtomType::Variable("myvariable".to_string())),
             Token::EOF,
         ];
@@ -360,4 +880,333 @@ mod lexer_tests {
         assert_eq!(lexed_tokens, expected_tokens);
         Ok(())
     }
+
+    #[test]
+    fn test_number_literal_grammar() -> Result<()> {
+        // Literals which should be accepted, with their values
+        let accepted: Vec<(&str, f64)> = vec![
+            ("0", 0.0),
+            ("7", 7.0),
+            ("42", 42.0),
+            ("007", 7.0),
+            ("000", 0.0),
+            ("0.5", 0.5),
+            ("00.5", 0.5),
+            ("3.14", 3.14),
+            ("10.0", 10.0),
+            ("1.000", 1.0),
+            ("123456789", 123456789.0),
+            ("0.000001", 0.000001),
+            ("2.50", 2.5),
+            ("99.99", 99.99),
+            ("18446744073709551616", 18446744073709551616.0),
+            ("1e5", 1e5),
+            ("2.5e-3", 2.5e-3),
+            ("6.022E23", 6.022e23),
+            ("1e+2", 100.0),
+            ("007e01", 70.0),
+            ("0x1F", 31.0),
+            ("0XFF", 255.0),
+            ("0o17", 15.0),
+            ("0b1010", 10.0),
+            ("0B0", 0.0),
+            ("1_000_000", 1_000_000.0),
+            ("3.141_592", 3.141_592),
+            ("3.14_15", 3.1415),
+            ("1_000", 1000.0),
+            ("1_.5", 1.5),
+            ("0.001_234", 0.001_234),
+            ("1_0.0_1e1_0", 10.01e10),
+            ("0xFF_FF", 65535.0),
+            ("0b1010_1010", 170.0),
+        ];
+        for (input, expected) in accepted {
+            let lexed_tokens = Lexer::new(input)?.lex()?;
+            let value = match lexed_tokens[..] {
+                [Token::Atom(AtomType::Number(n)), Token::EOF] => n,
+                [Token::Atom(AtomType::Integer(n)), Token::EOF] => n as f64,
+                _ => return Err(anyhow!("Lexing {input} gave {lexed_tokens:?}")),
+            };
+            assert_eq!(value, expected, "lexing {input}");
+        }
+
+        // Malformed literals, with part of the expected error message
+        let rejected: Vec<(&str, &str)> = vec![
+            ("1.", "Incomplete number literal 1."),
+            ("1.+2", "Incomplete number literal 1."),
+            ("1. 5", "Incomplete number literal 1."),
+            ("1.e5", "Incomplete number literal 1."),
+            ("1.5e", "Malformed number literal 1.5e"),
+            ("2x", "Malformed number literal 2x"),
+            (
+                "3_",
+                "Malformed number literal 3_, underscore separators must be between digits",
+            ),
+            (
+                "1__000",
+                "Malformed number literal 1_, underscore separators must be between digits",
+            ),
+            ("1_.", "Incomplete number literal 1_."),
+            ("1._5", "Incomplete number literal 1."),
+            (
+                "1e_5",
+                "Malformed number literal 1e, expected digits in the exponent",
+            ),
+            (
+                "0x_FF",
+                "Malformed number literal 0x_FF, underscore separators must be between digits",
+            ),
+            (
+                "0xFF_",
+                "Malformed number literal 0xFF_, underscore separators must be between digits",
+            ),
+            ("1.2.3", "two decimal points in number literal 1.2."),
+            ("07.5.2", "two decimal points in number literal 07.5."),
+            ("1..2", "Incomplete number literal 1."),
+            ("12abc", "Malformed number literal 12a"),
+            (".5", "Unexpected character encountered during lexing: ."),
+            ("5.a", "Incomplete number literal 5."),
+            ("9.9.", "two decimal points in number literal 9.9."),
+            (
+                "1e",
+                "Malformed number literal 1e, expected digits in the exponent",
+            ),
+            (
+                "1e+",
+                "Malformed number literal 1e+, expected digits in the exponent",
+            ),
+            (
+                "1e-x",
+                "Malformed number literal 1e-, expected digits in the exponent",
+            ),
+            (
+                "2e3.5",
+                "Malformed number literal 2e3., the exponent must be a whole number",
+            ),
+            ("1e2e3", "Malformed number literal 1e2e"),
+            ("1E5x", "Malformed number literal 1E5x"),
+            (
+                "0x",
+                "Incomplete number literal 0x, expected hexadecimal digits after 0x",
+            ),
+            (
+                "0b+1",
+                "Incomplete number literal 0b, expected binary digits after 0b",
+            ),
+            ("0b102", "Invalid digit 2 in binary literal 0b102"),
+            ("0o8", "Invalid digit 8 in octal literal 0o8"),
+            ("0xFG", "Invalid digit G in hexadecimal literal 0xFG"),
+            (
+                "0x1FFFFFFFFFFFFFFFF",
+                "The hexadecimal literal 0x1FFFFFFFFFFFFFFFF is too large",
+            ),
+        ];
+        for (input, expected) in rejected {
+            let err = match Lexer::new(input)?.lex() {
+                Ok(tokens) => {
+                    return Err(anyhow!("Lexing {input} should fail, got {tokens:?}"));
+                }
+                Err(err) => err.to_string(),
+            };
+            assert!(
+                err.contains(expected),
+                "lexing {input} gave error {err:?}, expected {expected:?}"
+            );
+        }
+
+        // Separate literals are separate tokens, whitespace or not
+        let lexed_tokens = Lexer::new("0 7")?.lex()?;
+        assert_eq!(
+            lexed_tokens,
+            vec![
+                Token::Atom(AtomType::Integer(0)),
+                Token::Atom(AtomType::Integer(7)),
+                Token::EOF
+            ]
+        );
+        let lexed_tokens = Lexer::new("1.5+007")?.lex()?;
+        assert_eq!(
+            lexed_tokens,
+            vec![
+                Token::Atom(AtomType::Number(1.5)),
+                Token::Op(Operator::Plus),
+                Token::Atom(AtomType::Integer(7)),
+                Token::EOF
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_integer() -> Result<()> {
+        let cases = [
+            ("42", AtomType::Integer(42)),
+            ("007", AtomType::Integer(7)),
+            ("42.0", AtomType::Number(42.0)),
+            ("42e0", AtomType::Number(42.0)),
+            ("0xFF", AtomType::Integer(255)),
+            ("9223372036854775807", AtomType::Integer(i64::MAX)),
+            (
+                "9223372036854775808",
+                AtomType::Number(9223372036854775808.0),
+            ),
+            (
+                "0xFFFFFFFFFFFFFFFF",
+                AtomType::Number(18446744073709551615.0),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                Lexer::new(input)?.lex()?,
+                vec![Token::Atom(expected), Token::EOF],
+                "lexing {input}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_underscore_separators() -> Result<()> {
+        let lexed_tokens = Lexer::new("1_000 * _2 + 2.5e1_0")?.lex()?;
+        assert_eq!(
+            lexed_tokens,
+            vec![
+                Token::Atom(AtomType::Integer(1000)),
+                Token::Op(Operator::Star),
+                // A leading underscore starts a variable, not a number
+                Token::Atom(AtomType::Variable("_2".to_string())),
+                Token::Op(Operator::Plus),
+                Token::Atom(AtomType::Number(2.5e10)),
+                Token::EOF
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_scientific_notation() -> Result<()> {
+        let lexed_tokens = Lexer::new("1e3 + 2.5e-2 * x - 4E+1")?.lex()?;
+        assert_eq!(
+            lexed_tokens,
+            vec![
+                Token::Atom(AtomType::Number(1000.0)),
+                Token::Op(Operator::Plus),
+                Token::Atom(AtomType::Number(0.025)),
+                Token::Op(Operator::Star),
+                Token::Atom(AtomType::Variable("x".to_string())),
+                Token::Op(Operator::Minus),
+                Token::Atom(AtomType::Number(40.0)),
+                Token::EOF
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_exponent_forms() -> Result<()> {
+        let cases = [
+            ("1e10", 10000000000.0),
+            ("3.14e-2", 0.0314),
+            ("2.5E+3", 2500.0),
+            ("6.022E23", 6.022e23),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                Lexer::new(input)?.lex()?,
+                vec![Token::Atom(AtomType::Number(expected)), Token::EOF],
+                "lexing {input}"
+            );
+        }
+        // An exponent needs digits, rather than e starting a variable
+        let err = Lexer::new("1e")?.lex().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Malformed number literal 1e, expected digits in the exponent"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_scale_suffixes() -> Result<()> {
+        let cases = [
+            ("3.5%", AtomType::Percent(3.5)),
+            ("2‰", AtomType::Number(0.002)),
+            ("150%", AtomType::Percent(150.0)),
+            ("1_000‰", AtomType::Number(1.0)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                Lexer::new(input)?.lex()?,
+                vec![Token::Atom(expected), Token::EOF],
+                "lexing {input}"
+            );
+        }
+        // A % followed by an operand is the remainder operator
+        assert_eq!(
+            Lexer::new("10%3 + 5% * x")?.lex()?,
+            vec![
+                Token::Atom(AtomType::Integer(10)),
+                Token::Op(Operator::Percent),
+                Token::Atom(AtomType::Integer(3)),
+                Token::Op(Operator::Plus),
+                Token::Atom(AtomType::Percent(5.0)),
+                Token::Op(Operator::Star),
+                Token::Atom(AtomType::Variable("x".to_string())),
+                Token::EOF
+            ]
+        );
+        // Only numbers take suffixes
+        assert_eq!(
+            Lexer::new("x%")?.lex()?,
+            vec![
+                Token::Atom(AtomType::Variable("x".to_string())),
+                Token::Op(Operator::Percent),
+                Token::EOF
+            ]
+        );
+        let err = Lexer::new("x‰")?.lex().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The permille sign ‰ must directly follow a number, as in 2‰"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_unicode_variable() -> Result<()> {
+        let mut test_lexer = Lexer::new("λ*σ_2 + _ñ")?;
+        let lexed_tokens = test_lexer.lex()?;
+        let expected_tokens: Vec<Token> = vec![
+            Token::Atom(AtomType::Variable("λ".to_string())),
+            Token::Op(Operator::Star),
+            Token::Atom(AtomType::Variable("σ_2".to_string())),
+            Token::Op(Operator::Plus),
+            Token::Atom(AtomType::Variable("_ñ".to_string())),
+            Token::EOF,
+        ];
+        assert_eq!(lexed_tokens, expected_tokens);
+        // Digits and operators still can't start a variable name
+        assert!(Lexer::new("2σ")?.lex().is_err());
+        assert!(Lexer::new("×σ")?.lex().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lex_keyword() -> Result<()> {
+        let lexed_tokens = Lexer::new("u where u = 2, wherever")?.lex()?;
+        assert_eq!(
+            lexed_tokens,
+            vec![
+                Token::Atom(AtomType::Variable("u".to_string())),
+                Token::Keyword(Keyword::Where),
+                Token::Atom(AtomType::Variable("u".to_string())),
+                Token::Op(Operator::Assign),
+                Token::Atom(AtomType::Integer(2)),
+                Token::Op(Operator::Comma),
+                Token::Atom(AtomType::Variable("wherever".to_string())),
+                Token::EOF,
+            ]
+        );
+        Ok(())
+    }
 }
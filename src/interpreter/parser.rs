@@ -1,17 +1,22 @@
 // Standard Library Uses
+use std::collections::BTreeMap;
 use std::fmt;
 
 // External Crate Uses
 use anyhow::{Context, Result, anyhow};
 
 // Local Uses
-use super::lexer::{AtomType, Lexer, Token};
+use super::interpreter::builtin_arity;
+use super::lexer::{AtomType, Keyword, Lexer, Operator, Token};
 
 /// An S-expression
 #[derive(Clone, Debug)]
-pub(crate) enum SExpr {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SExpr {
     Atom(SExprAtom),
     Cons(SExprAtom, Vec<SExpr>),
+    /// A body evaluated with local bindings, made in order
+    Where(Box<SExpr>, Vec<(String, SExpr)>),
 }
 
 impl fmt::Display for SExpr {
@@ -27,19 +32,151 @@ impl fmt::Display for SExpr {
                 }
                 write!(f, ")")
             }
+            SExpr::Where(body, bindings) => {
+                write!(f, "(where {}", body)?;
+                for (name, value) in bindings {
+                    write!(f, " (= {} {})", name, value)?
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl SExpr {
+    /// Render the expression in infix notation, with every nested
+    /// operation wrapped in parenthesis
+    pub fn to_infix(&self) -> String {
+        match self {
+            SExpr::Atom(at) => at.to_string(),
+            // The brackets already group their contents
+            SExpr::Cons(SExprAtom::Op(Operator::LFloor), operands) if operands.len() == 1 => {
+                format!("⌊{}⌋", operands[0].to_infix())
+            }
+            SExpr::Cons(SExprAtom::Op(Operator::LCeil), operands) if operands.len() == 1 => {
+                format!("⌈{}⌉", operands[0].to_infix())
+            }
+            SExpr::Cons(SExprAtom::Variable(name), arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(SExpr::to_infix)
+                    .collect::<Vec<String>>();
+                format!("{name}({})", arguments.join(", "))
+            }
+            SExpr::Cons(op, operands) => {
+                let operands = operands
+                    .iter()
+                    .map(SExpr::to_nested_infix)
+                    .collect::<Vec<String>>();
+                match (op, &operands[..]) {
+                    (SExprAtom::Op(Operator::Bang), [operand]) => format!("{operand}!"),
+                    (SExprAtom::Op(Operator::Percent), [operand]) => format!("{operand}%"),
+                    (SExprAtom::Op(Operator::Not), [operand]) => format!("not {operand}"),
+                    (SExprAtom::Op(Operator::Question), [condition, then_branch, else_branch]) => {
+                        format!("{condition} ? {then_branch} : {else_branch}")
+                    }
+                    (op, [operand]) => format!("{op}{operand}"),
+                    (op, [lhs, rhs]) => format!("{lhs} {op} {rhs}"),
+                    (op, operands) => format!("{op}({})", operands.join(", ")),
+                }
+            }
+            SExpr::Where(body, bindings) => {
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, value)| format!("{name} = {}", value.to_nested_infix()))
+                    .collect::<Vec<String>>();
+                format!("{} where {}", body.to_nested_infix(), bindings.join(", "))
+            }
+        }
+    }
+
+    /// Render the expression in reverse Polish (postfix) notation, e.g.
+    /// `3 + 4 * 2` as `3 4 2 * +`
+    ///
+    /// Prefix minus is written `neg` so it can't be mistaken for
+    /// subtraction, prefix plus is dropped, floor and ceiling are written
+    /// `floor` and `ceil`, and function calls are written as the function
+    /// name after its arguments, built in functions only with the fewest
+    /// arguments they take. Where clauses have no postfix form, as
+    /// their bindings only exist while the body is evaluated.
+    pub fn to_rpn(&self) -> Result<String> {
+        match self {
+            SExpr::Atom(at) => Ok(at.to_string()),
+            SExpr::Cons(op, operands) => {
+                let mut tokens = operands
+                    .iter()
+                    .map(SExpr::to_rpn)
+                    .collect::<Result<Vec<String>>>()?;
+                match (op, operands.len()) {
+                    (SExprAtom::Op(Operator::Plus), 1) => {}
+                    // Written as a suffix, as the lexer only reads a % with
+                    // nothing following it as a percentage
+                    (SExprAtom::Op(Operator::Percent), 1) => {
+                        if let Some(operand) = tokens.last_mut() {
+                            operand.push('%');
+                        }
+                    }
+                    (SExprAtom::Op(Operator::Minus), 1) => tokens.push("neg".to_string()),
+                    (SExprAtom::Op(Operator::LFloor), 1) => tokens.push("floor".to_string()),
+                    (SExprAtom::Op(Operator::LCeil), 1) => tokens.push("ceil".to_string()),
+                    // Built in functions are read back with the fewest
+                    // arguments they take
+                    (SExprAtom::Variable(name), count)
+                        if builtin_arity(name).is_some_and(|(arity, _)| arity != count) =>
+                    {
+                        return Err(anyhow!(
+                            "Can't write {name} with {count} arguments in reverse Polish notation"
+                        ));
+                    }
+                    (op, _) => tokens.push(op.to_string()),
+                }
+                Ok(tokens.join(" "))
+            }
+            SExpr::Where(..) => Err(anyhow!(
+                "Can't write a where clause in reverse Polish notation"
+            )),
+        }
+    }
+
+    /// A percentage, the number written before a percent sign, as in `10%`
+    ///
+    /// On its own a percentage is its number divided by 100, but as the
+    /// right hand side of `+` or `-` it is relative to the left hand side,
+    /// so `200 + 10%` is 220.
+    pub fn percent(n: f64) -> SExpr {
+        SExpr::Cons(
+            SExprAtom::Op(Operator::Percent),
+            vec![SExpr::Atom(SExprAtom::Number(n))],
+        )
+    }
+
+    /// Check whether the expression is a percentage made by [`SExpr::percent`]
+    pub fn is_percent(&self) -> bool {
+        matches!(self, SExpr::Cons(SExprAtom::Op(Operator::Percent), operands) if operands.len() == 1)
+    }
+
+    /// Render the expression in infix notation as an operand of another
+    /// expression, wrapping it in parenthesis unless it is an atom
+    fn to_nested_infix(&self) -> String {
+        match self {
+            SExpr::Atom(_) => self.to_infix(),
+            _ => format!("({})", self.to_infix()),
         }
     }
 }
 
 /// An S-expression atom
 #[derive(Clone, Debug)]
-pub(crate) enum SExprAtom {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SExprAtom {
     /// An operation such as +, -, etc.
-    Op(char),
+    Op(Operator),
     /// A variable identifier
     Variable(String),
     /// A floating point number
     Number(f64),
+    /// A whole number, written without a decimal point or exponent
+    Integer(i64),
 }
 
 impl fmt::Display for SExprAtom {
@@ -54,22 +191,72 @@ impl fmt::Display for SExprAtom {
             SExprAtom::Number(num) => {
                 write!(f, "{}", num)
             }
+            SExprAtom::Integer(num) => {
+                write!(f, "{}", num)
+            }
         }
     }
 }
 
+/// An error encountered during parsing, with an optional suggested fix
+#[derive(Debug)]
+pub struct ParseError {
+    /// Description of what went wrong
+    message: String,
+    /// A suggestion for how the input could be fixed
+    suggestion: Option<String>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// Find the suggested fix attached to a parsing error, if there is one
+    pub fn suggestion(err: &anyhow::Error) -> Option<&str> {
+        err.chain()
+            .find_map(|e| e.downcast_ref::<ParseError>())
+            .and_then(|e| e.suggestion.as_deref())
+    }
+}
+
 /// Parses sequences of Tokens into S-expressions
-pub(crate) struct PrattParser {
+pub struct PrattParser {
     /// Series of tokens to parse
     tokens: Vec<Token>,
+    /// The most recently consumed token
+    last: Option<Token>,
+    /// The token consumed before the most recent one
+    previous: Option<Token>,
+    /// Binding powers of the infix operators
+    binding_powers: BindingPowers,
 }
 
 // Main Parsing Functions
 impl PrattParser {
     /// Parse a string into an S-expression
-    pub(crate) fn parse(input: &str) -> Result<SExpr> {
-        let mut parser = PrattParser::new(input)?;
-        Ok(parser.parse_min_bp(0u8)?)
+    pub fn parse(input: &str) -> Result<SExpr> {
+        Self::parse_with(input, &BindingPowers::default())
+    }
+
+    /// Parse a string into an S-expression, using the given binding
+    /// powers for the infix operators
+    pub fn parse_with(input: &str, binding_powers: &BindingPowers) -> Result<SExpr> {
+        let mut parser = PrattParser::new(input, binding_powers.clone())?;
+        let sexpr = parser.parse_min_bp(0u8)?;
+        // Everything should have been consumed by the expression
+        match parser.peek()? {
+            Token::EOF => Ok(sexpr),
+            t => Err(parser.error(
+                format!("Unexpected token {t} after the end of the expression"),
+                parser.last.as_ref(),
+                &t,
+            )),
+        }
     }
 
     fn parse_min_bp(&mut self, min_bp: u8) -> Result<SExpr> {
@@ -83,22 +270,62 @@ impl PrattParser {
         {
             Token::Atom(at) => match at {
                 AtomType::Number(n) => SExpr::Atom(SExprAtom::Number(n)),
+                AtomType::Integer(n) => SExpr::Atom(SExprAtom::Integer(n)),
+                AtomType::Percent(n) => SExpr::percent(n),
+                // A name followed by parenthesis is a function call
+                AtomType::Variable(varname) if self.peek()? == Token::Op(Operator::LParen) => {
+                    self.consume()?;
+                    let arguments = self.parse_arguments(&varname)?;
+                    SExpr::Cons(SExprAtom::Variable(varname), arguments)
+                }
                 AtomType::Variable(varname) => SExpr::Atom(SExprAtom::Variable(varname)),
             },
-            Token::Op('(') => {
+            Token::Op(Operator::LParen) => {
                 let lhs = self.parse_min_bp(0u8)?;
-                if self.pop()? != Token::Op(')') {
+                if self.pop()? != Token::Op(Operator::RParen) {
                     return Err(anyhow!("Unmatched paranthesis encountered during parsing"));
                 }
                 lhs
             }
+            // Floor and ceiling brackets group like parenthesis, then
+            // apply their function to the inner expression
+            Token::Op(open @ (Operator::LFloor | Operator::LCeil)) => {
+                let close = if open == Operator::LFloor {
+                    Operator::RFloor
+                } else {
+                    Operator::RCeil
+                };
+                let inner = self.parse_min_bp(0u8)?;
+                match self.pop()? {
+                    Token::Op(c) if c == close => {}
+                    t => {
+                        return Err(anyhow!(
+                            "Unmatched {open} encountered during parsing, expected {close} but found {t}"
+                        ));
+                    }
+                }
+                SExpr::Cons(SExprAtom::Op(open), vec![inner])
+            }
+            Token::Keyword(Keyword::Not) => {
+                let ((), bp) = Self::prefix_binding_power(&Operator::Not)?;
+                let rhs = self.parse_min_bp(bp)?;
+                SExpr::Cons(SExprAtom::Op(Operator::Not), vec![rhs])
+            }
             Token::Op(op) => {
-                let ((), bp) = Self::prefix_binding_power(&op).context(
-                    "Trying to determine binding power of first token encountered in Pratt Parser",
-                )?;
+                let ((), bp) = Self::prefix_binding_power(&op)
+                    .map_err(|err| {
+                        self.error(err.to_string(), self.previous.as_ref(), &Token::Op(op))
+                    })
+                    .context(
+                        "Trying to determine binding power of first token encountered in Pratt Parser",
+                    )?;
                 let rhs = self.parse_min_bp(bp)?;
                 SExpr::Cons(SExprAtom::Op(op), vec![rhs])
             }
+            // Nothing at all has been consumed, so there is no expression
+            Token::EOF if self.previous.is_none() => {
+                return Err(anyhow!("Empty input, expected an expression"));
+            }
             t => return Err(anyhow!("Encountered bad token during parsing {t}")),
         };
 
@@ -112,14 +339,40 @@ impl PrattParser {
                 .context("Peeking next token during rhs parsing loop")?
             {
                 Token::EOF => break,
+                // A where clause binds more loosely than anything else, so
+                // it only applies to a whole (possibly parenthesized)
+                // expression
+                Token::Keyword(Keyword::Where) if min_bp > 0 => break,
+                Token::Keyword(Keyword::Where) => {
+                    self.consume()?;
+                    lhs = SExpr::Where(Box::new(lhs), self.parse_bindings()?);
+                    continue;
+                }
                 Token::Op(op) => op,
+                // The keyword forms of && and ||
+                Token::Keyword(Keyword::And) => Operator::And,
+                Token::Keyword(Keyword::Or) => Operator::Or,
                 t => {
-                    return Err(anyhow!(
-                        "Encountered unknown token {t} during rhs parsing loop"
+                    return Err(self.error(
+                        format!("Encountered unknown token {t} during rhs parsing loop"),
+                        self.last.as_ref(),
+                        &t,
                     ));
                 }
             };
 
+            // A conditional binds more loosely than everything but a
+            // where clause
+            if op == Operator::Question {
+                let (l_bp, r_bp) = CONDITIONAL_BINDING_POWERS;
+                if l_bp < min_bp {
+                    break;
+                }
+                self.consume()?;
+                lhs = self.parse_conditional(lhs, r_bp)?;
+                continue;
+            }
+
             // Start by seeing if this operator may be a postfix operator
             if let Some((pf_bp, ())) = Self::postfix_binding_power(&op) {
                 // If the postfix binding power is too low,
@@ -141,7 +394,7 @@ impl PrattParser {
 
             // If the operation is not a postfix operator,
             // process it as an infix operator
-            if let Some((l_bp, r_bp)) = Self::infix_binding_power(&op) {
+            if let Some((l_bp, r_bp)) = self.binding_powers.infix(op) {
                 // Check if the binding power is too low
                 if l_bp < min_bp {
                     // Note: Since we are binding it to the left expression,
@@ -172,36 +425,266 @@ impl PrattParser {
     }
 }
 
-// Operator Binding Powers
+// Function Calls
+impl PrattParser {
+    /// Parse the comma separated arguments of a call to the function
+    /// name, up to and including the closing parenthesis, allowing a
+    /// trailing comma
+    fn parse_arguments(&mut self, name: &str) -> Result<Vec<SExpr>> {
+        let mut arguments = Vec::new();
+        if self.peek()? == Token::Op(Operator::RParen) {
+            self.consume()?;
+            return Ok(arguments);
+        }
+        loop {
+            let argument = self
+                .parse_min_bp(0u8)
+                .with_context(|| format!("Failed to parse argument of {name}"))?;
+            arguments.push(argument);
+            match self.pop()? {
+                Token::Op(Operator::Comma) => {
+                    if self.peek()? == Token::Op(Operator::RParen) {
+                        self.consume()?;
+                        return Ok(arguments);
+                    }
+                }
+                Token::Op(Operator::RParen) => return Ok(arguments),
+                t => {
+                    return Err(anyhow!(
+                        "Expected , or ) after argument of {name}, found {t}"
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// Conditionals
 impl PrattParser {
+    /// Parse the branches of a conditional with the given condition,
+    /// after its `?`, parsing the else branch with binding power r_bp
+    fn parse_conditional(&mut self, condition: SExpr, r_bp: u8) -> Result<SExpr> {
+        // The then branch ends at the :, so can hold anything
+        let then_branch = self
+            .parse_min_bp(0u8)
+            .context("Failed to parse then branch of conditional")?;
+        match self.pop()? {
+            Token::Op(Operator::Colon) => {}
+            t => {
+                return Err(anyhow!(
+                    "Expected : after then branch of conditional, found {t}"
+                ));
+            }
+        }
+        let else_branch = self
+            .parse_min_bp(r_bp)
+            .context("Failed to parse else branch of conditional")?;
+        Ok(SExpr::Cons(
+            SExprAtom::Op(Operator::Question),
+            vec![condition, then_branch, else_branch],
+        ))
+    }
+}
+
+// Where Clauses
+impl PrattParser {
+    /// Parse the comma separated `name = value` bindings of a where clause
+    fn parse_bindings(&mut self) -> Result<Vec<(String, SExpr)>> {
+        let mut bindings = Vec::new();
+        loop {
+            // The binding is parsed as an assignment, binding tightly
+            // enough to stop at the next comma or where
+            let binding = self
+                .parse_min_bp(2u8)
+                .context("Failed to parse binding of where clause")?;
+            match binding {
+                SExpr::Cons(SExprAtom::Op(Operator::Assign), mut operands)
+                    if operands.len() == 2 =>
+                {
+                    let value = operands.pop();
+                    match (operands.pop(), value) {
+                        (Some(SExpr::Atom(SExprAtom::Variable(name))), Some(value)) => {
+                            bindings.push((name, value))
+                        }
+                        (Some(target), _) => {
+                            return Err(anyhow!(
+                                "Can only bind a variable in a where clause, not {target}"
+                            ));
+                        }
+                        _ => return Err(anyhow!("Malformed binding in where clause")),
+                    }
+                }
+                binding => {
+                    return Err(anyhow!(
+                        "Expected a binding of the form name = value in where clause, found {binding}"
+                    ));
+                }
+            }
+            if self.peek()? != Token::Op(Operator::Comma) {
+                return Ok(bindings);
+            }
+            self.consume()?;
+        }
+    }
+}
+
+/// Left and right binding powers of the `?` of a conditional
+///
+/// Binds more loosely than assignment, but still tightly enough that a
+/// following where clause applies to the whole conditional. Equal powers
+/// make it group to the right, so `a ? b : c ? d : e` is
+/// `a ? b : (c ? d : e)`.
+pub(super) const CONDITIONAL_BINDING_POWERS: (u8, u8) = (1, 1);
+
+/// Default left and right binding powers of the infix operators
+const DEFAULT_INFIX_BINDING_POWERS: &[(Operator, (u8, u8))] = &[
+    (Operator::Assign, (2, 1)),
+    (Operator::PlusAssign, (2, 1)),
+    (Operator::MinusAssign, (2, 1)),
+    (Operator::StarAssign, (2, 1)),
+    (Operator::SlashAssign, (2, 1)),
+    (Operator::PercentAssign, (2, 1)),
+    (Operator::CaretAssign, (2, 1)),
+    (Operator::Or, (3, 4)),
+    (Operator::And, (5, 6)),
+    (Operator::BitOr, (7, 8)),
+    (Operator::BitAnd, (9, 10)),
+    (Operator::ShiftLeft, (11, 12)),
+    (Operator::ShiftRight, (11, 12)),
+    (Operator::Equal, (13, 14)),
+    (Operator::NotEqual, (13, 14)),
+    (Operator::Less, (15, 16)),
+    (Operator::LessEqual, (15, 16)),
+    (Operator::Greater, (15, 16)),
+    (Operator::GreaterEqual, (15, 16)),
+    (Operator::Plus, (17, 18)),
+    (Operator::Minus, (17, 18)),
+    (Operator::Caret, (20, 19)),
+    (Operator::Star, (21, 22)),
+    (Operator::Slash, (21, 22)),
+    (Operator::DoubleSlash, (21, 22)),
+    (Operator::Percent, (21, 22)),
+];
+
+/// Which operator assigns to a variable
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum AssignmentStyle {
+    /// `x = 5` assigns, and `x == 5` compares
+    #[default]
+    Equals,
+    /// Pascal style, `x := 5` assigns, and `x = 5` compares like `==`
+    ColonEquals,
+}
+
+impl fmt::Display for AssignmentStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssignmentStyle::Equals => write!(f, "{}", Operator::Assign),
+            AssignmentStyle::ColonEquals => write!(f, "{}", Operator::ColonAssign),
+        }
+    }
+}
+
+impl AssignmentStyle {
+    /// Look up an assignment style by its assignment operator
+    pub fn from_name(name: &str) -> Result<Self> {
+        match Operator::from_symbol(name) {
+            Some(Operator::Assign) => Ok(AssignmentStyle::Equals),
+            Some(Operator::ColonAssign) => Ok(AssignmentStyle::ColonEquals),
+            _ => Err(anyhow!(
+                "Unknown assignment operator {name}, expected = or :="
+            )),
+        }
+    }
+}
+
+/// The table of infix operator binding powers used while parsing, which
+/// can be changed to experiment with precedence, along with which
+/// operator assigns
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingPowers {
+    /// Left and right binding powers of each infix operator
+    infix: BTreeMap<Operator, (u8, u8)>,
+    /// Which operator assigns to a variable
+    assignment_style: AssignmentStyle,
+}
+
+impl Default for BindingPowers {
+    fn default() -> Self {
+        BindingPowers {
+            infix: DEFAULT_INFIX_BINDING_POWERS.iter().copied().collect(),
+            assignment_style: AssignmentStyle::default(),
+        }
+    }
+}
+
+impl BindingPowers {
     /// Determine the infix binding power of the operator
-    /// represented by c
-    fn infix_binding_power(c: &char) -> Option<(u8, u8)> {
-        match c {
-            '=' => Some((2, 1)),
-            '+' | '-' => Some((3, 4)),
-            '^' => Some((6, 5)),
-            '*' | '/' => Some((7, 8)),
-            _ => None,
+    pub fn infix(&self, op: Operator) -> Option<(u8, u8)> {
+        self.infix.get(&op).copied()
+    }
+
+    /// Every infix operator with its left and right binding powers
+    pub fn infix_operators(&self) -> Vec<(Operator, (u8, u8))> {
+        self.infix
+            .iter()
+            .map(|(op, powers)| (*op, *powers))
+            .collect()
+    }
+
+    /// Which operator assigns to a variable
+    pub fn assignment_style(&self) -> AssignmentStyle {
+        self.assignment_style
+    }
+
+    /// Change which operator assigns to a variable
+    pub fn set_assignment_style(&mut self, style: AssignmentStyle) {
+        self.assignment_style = style;
+    }
+
+    /// Change the binding powers of an existing infix operator
+    ///
+    /// Binding powers must be at least 1, as 0 is the binding power of a
+    /// whole expression, and an operator binding that loosely would
+    /// swallow a following where clause.
+    pub fn set_infix(&mut self, op: Operator, left: u8, right: u8) -> Result<()> {
+        let Some(powers) = self.infix.get_mut(&op) else {
+            let mut symbols = self.infix.keys().map(|op| op.symbol()).collect::<Vec<_>>();
+            symbols.sort();
+            return Err(anyhow!(
+                "Unknown infix operator {op}, the infix operators are {}",
+                symbols.join(" ")
+            ));
+        };
+        if left == 0 || right == 0 {
+            return Err(anyhow!("Binding powers must be at least 1"));
         }
+        *powers = (left, right);
+        Ok(())
     }
+}
 
+// Operator Binding Powers
+impl PrattParser {
     /// Determine the prefix binding power of the operator
-    /// represented by c
-    fn prefix_binding_power(c: &char) -> Result<((), u8)> {
-        match c {
-            '+' | '-' => Ok(((), 9)),
+    pub(super) fn prefix_binding_power(op: &Operator) -> Result<((), u8)> {
+        match op {
+            Operator::Plus | Operator::Minus | Operator::Tilde => Ok(((), 23)),
+            // Binds more loosely than comparisons and bitwise operators
+            // but more tightly than and, so `not a == b and c` is
+            // `(not (a == b)) and c`
+            Operator::Not => Ok(((), 7)),
             _ => Err(anyhow!(
-                "Character {c} does not have an associated prefix binding power"
+                "Character {op} does not have an associated prefix binding power"
             )),
         }
     }
 
     /// Determine the postfix binding power of the operator
-    /// represented by c
-    fn postfix_binding_power(c: &char) -> Option<(u8, ())> {
-        match c {
-            '!' => Some((11, ())),
+    pub(super) fn postfix_binding_power(op: &Operator) -> Option<(u8, ())> {
+        match op {
+            Operator::Bang => Some((25, ())),
             _ => None,
         }
     }
@@ -210,16 +693,91 @@ impl PrattParser {
 // Utility functions for the Parser
 impl PrattParser {
     /// Create a new Parser from a string input
-    fn new(input: &str) -> Result<Self> {
+    fn new(input: &str, binding_powers: BindingPowers) -> Result<Self> {
+        // Quickly reject input with unbalanced parenthesis before lexing
+        Self::check_balance(input)?;
         // Create a parser from the input
         let mut parser_lexer = Lexer::new(input)?;
         // Lex the input into a series of tokens
         let mut tokens = parser_lexer
             .lex()
             .context("Failed to parse input to parser")?;
+        // In the Pascal style := is parsed as an assignment and = as an
+        // equality, otherwise := is left without a binding power
+        if binding_powers.assignment_style == AssignmentStyle::ColonEquals {
+            for token in &mut tokens {
+                match token {
+                    Token::Op(op @ Operator::Assign) => *op = Operator::Equal,
+                    Token::Op(op @ Operator::ColonAssign) => *op = Operator::Assign,
+                    _ => {}
+                }
+            }
+        }
         // Reverse the tokens to make popping easier
         tokens.reverse();
-        Ok(Self { tokens })
+        Ok(Self {
+            tokens,
+            last: None,
+            previous: None,
+            binding_powers,
+        })
+    }
+
+    /// Create a parsing error for an unexpected token, attaching a
+    /// suggested fix if it and the token before it match a common mistake
+    fn error(&self, message: String, before: Option<&Token>, token: &Token) -> anyhow::Error {
+        let suggestion = match (before, token) {
+            // An operator following one which still needs an operand
+            (Some(Token::Op(prev)), Token::Op(op))
+                if !matches!(prev, Operator::RParen | Operator::Bang)
+                    && !matches!(op, Operator::LParen | Operator::RParen)
+                    && Self::prefix_binding_power(op).is_err() =>
+            {
+                Some(format!(
+                    "did you forget an operand between `{prev}` and `{op}`?"
+                ))
+            }
+            // Two operands with no operator between them
+            (
+                Some(prev @ (Token::Atom(_) | Token::Op(Operator::RParen | Operator::Bang))),
+                Token::Atom(_) | Token::Op(Operator::LParen),
+            ) => Some(format!(
+                "missing operator — did you mean `{prev} * {token}`?"
+            )),
+            _ => None,
+        };
+        ParseError {
+            message,
+            suggestion,
+        }
+        .into()
+    }
+
+    /// Check that the parenthesis in the input are balanced, reporting
+    /// how many are unclosed or unmatched if not
+    fn check_balance(input: &str) -> Result<()> {
+        let mut depth = 0usize;
+        let mut unmatched = 0usize;
+        for c in input.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => unmatched += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        let plural = |n: usize| if n == 1 { "parenthesis" } else { "parentheses" };
+        if unmatched > 0 {
+            return Err(ParseError {
+                message: format!("{unmatched} unmatched closing {}", plural(unmatched)),
+                suggestion: Some("remove this `)` or add a matching `(`".to_string()),
+            }
+            .into());
+        }
+        if depth > 0 {
+            return Err(anyhow!("{depth} unclosed {}", plural(depth)));
+        }
+        Ok(())
     }
 
     /// Get the next token without consuming it
@@ -229,7 +787,9 @@ impl PrattParser {
 
     /// Get the next token and consume it
     fn pop(&mut self) -> Result<Token> {
-        Ok(self.tokens.pop().unwrap_or(Token::EOF))
+        let token = self.tokens.pop().unwrap_or(Token::EOF);
+        self.previous = self.last.replace(token.clone());
+        Ok(token)
     }
 
     /// Consume the next token, returning nothing
@@ -239,7 +799,21 @@ impl PrattParser {
     }
 }
 
+#[cfg(feature = "serde")]
+impl SExpr {
+    /// Serialize the expression into a compact binary form
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).context("Failed to serialize S-expression")
+    }
+
+    /// Load an expression serialized with [`SExpr::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SExpr> {
+        bincode::deserialize(bytes).context("Failed to deserialize S-expression")
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::approx_constant, clippy::needless_return)]
 mod test_parser {
     use super::*;
 
@@ -277,6 +851,314 @@ mod test_parser {
         let parsed_res = PrattParser::parse(program)?;
         let expected = "(+ 3 (* 5 6))";
         assert_eq!(parsed_res.to_string(), expected);
+        let parsed_res = PrattParser::parse("10 % 3 + 1")?;
+        assert_eq!(parsed_res.to_string(), "(+ (% 10 3) 1)");
+        // Comparisons bind more loosely than arithmetic, but more tightly
+        // than assignment
+        let parsed_res = PrattParser::parse("c = a + 1 <= 2 * b")?;
+        assert_eq!(parsed_res.to_string(), "(= c (<= (+ a 1) (* 2 b)))");
+        // && binds more tightly than ||, and both more loosely than
+        // comparisons
+        let parsed_res = PrattParser::parse("a < 1 || b && c == 2")?;
+        assert_eq!(parsed_res.to_string(), "(|| (< a 1) (&& b (== c 2)))");
+        // Bitwise operators bind more loosely than arithmetic and
+        // comparisons, shifts most tightly and | least
+        let parsed_res = PrattParser::parse("a | b & c << d + 1")?;
+        assert_eq!(parsed_res.to_string(), "(| a (& b (<< c (+ d 1))))");
+        let parsed_res = PrattParser::parse("a >> 1 == b & ~c")?;
+        assert_eq!(parsed_res.to_string(), "(& (>> a (== 1 b)) (~ c))");
+        assert_eq!(parsed_res.to_rpn()?, "a 1 b == >> c ~ &");
+        let parsed_res = PrattParser::parse("a & b || c | d")?;
+        assert_eq!(parsed_res.to_string(), "(|| (& a b) (| c d))");
+        // Conditionals bind most loosely and group to the right, with
+        // anything allowed in the then branch
+        let parsed_res = PrattParser::parse("a || b ? c = 1 : d ? e : f + 1")?;
+        assert_eq!(
+            parsed_res.to_string(),
+            "(? (|| a b) (= c 1) (? d e (+ f 1)))"
+        );
+        assert_eq!(
+            parsed_res.to_infix(),
+            "(a || b) ? (c = 1) : (d ? e : (f + 1))"
+        );
+        assert_eq!(parsed_res.to_rpn()?, "a b || c 1 = d e f 1 + ? ?");
+        let parsed_res = PrattParser::parse("x = c ? 1 : 2 where c = 0")?;
+        assert_eq!(parsed_res.to_string(), "(where (= x (? c 1 2)) (= c 0))");
+        let err = PrattParser::parse("c ? 1, 2").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected : after then branch of conditional, found ,"
+        );
+        // The keyword forms are the same operators, with not binding more
+        // tightly than and but more loosely than comparisons
+        let parsed_res = PrattParser::parse("not 1 or 1")?;
+        assert_eq!(parsed_res.to_string(), "(|| (not 1) 1)");
+        let parsed_res = PrattParser::parse("a or not b == c and d")?;
+        assert_eq!(parsed_res.to_string(), "(|| a (&& (not (== b c)) d))");
+        assert_eq!(parsed_res.to_infix(), "a || ((not (b == c)) && d)");
+        assert_eq!(parsed_res.to_rpn()?, "a b c == not d && ||");
+        let parsed_res = PrattParser::parse("200 - 10% * 2")?;
+        assert_eq!(parsed_res.to_string(), "(- 200 (* (% 10) 2))");
+        assert_eq!(parsed_res.to_infix(), "200 - ((10%) * 2)");
+        assert_eq!(parsed_res.to_rpn()?, "200 10% 2 * -");
+        // Augmented assignments group to the right like =
+        let parsed_res = PrattParser::parse("a += b *= 2 + 1")?;
+        assert_eq!(parsed_res.to_string(), "(+= a (*= b (+ 2 1)))");
+        let parsed_res = PrattParser::parse("6//4/2")?;
+        assert_eq!(parsed_res.to_string(), "(/ (// 6 4) 2)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_infix_rendering() -> Result<()> {
+        let parsed_res = PrattParser::parse("a = -(1+2)*3! + 4^-b")?;
+        assert_eq!(
+            parsed_res.to_infix(),
+            "a = (((-(1 + 2)) * (3!)) + (4 ^ (-b)))"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unbalanced_parenthesis() -> Result<()> {
+        let err = PrattParser::parse("((3+4)").unwrap_err();
+        assert_eq!(err.to_string(), "1 unclosed parenthesis");
+        let err = PrattParser::parse("((3+4").unwrap_err();
+        assert_eq!(err.to_string(), "2 unclosed parentheses");
+        let err = PrattParser::parse("(3+4))").unwrap_err();
+        assert_eq!(err.to_string(), "1 unmatched closing parenthesis");
+        let err = PrattParser::parse(")3+4(").unwrap_err();
+        assert_eq!(err.to_string(), "1 unmatched closing parenthesis");
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_suggestions() -> Result<()> {
+        let suggestion = |program: &str| {
+            let err = PrattParser::parse(program).unwrap_err();
+            ParseError::suggestion(&err).map(|s| s.to_string())
+        };
+        assert_eq!(
+            suggestion("3 + * 4").as_deref(),
+            Some("did you forget an operand between `+` and `*`?")
+        );
+        assert_eq!(
+            suggestion("2 3").as_deref(),
+            Some("missing operator — did you mean `2 * 3`?")
+        );
+        assert_eq!(
+            suggestion("(1 + 2) (3)").as_deref(),
+            Some("missing operator — did you mean `) * (`?")
+        );
+        assert_eq!(
+            suggestion("3 + 4)").as_deref(),
+            Some("remove this `)` or add a matching `(`")
+        );
+        // Unrelated errors have no suggestion
+        assert_eq!(suggestion("3 +"), None);
+        assert_eq!(suggestion("* 4"), None);
+        assert_eq!(suggestion("()"), None);
         Ok(())
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_binary_round_trip() -> Result<()> {
+        let parsed = PrattParser::parse("a = -(1.5 + b) * 3! ^ 0.1")?;
+        let reloaded = SExpr::from_bytes(&parsed.to_bytes()?)?;
+        assert_eq!(reloaded.to_string(), parsed.to_string());
+        assert_eq!(reloaded.to_infix(), parsed.to_infix());
+        assert!(SExpr::from_bytes(&[0xff]).is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn test_empty_input() -> Result<()> {
+        for program in ["", "   ", "\t \n"] {
+            let err = PrattParser::parse(program).unwrap_err();
+            assert_eq!(err.to_string(), "Empty input, expected an expression");
+        }
+        // A missing operand after an operator is a different error
+        let err = PrattParser::parse("3 +").unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Failed to parse right hand side of infix operator during parsing: \
+            Encountered bad token during parsing EOF"
+        );
+        return Ok(());
+    }
+
+    #[test]
+    fn test_where_parsing() -> Result<()> {
+        let parsed = PrattParser::parse("u^2 + u where u = x + 1, v = 2")?;
+        assert_eq!(
+            parsed.to_string(),
+            "(where (+ (^ u 2) u) (= u (+ x 1)) (= v 2))"
+        );
+        assert_eq!(parsed.to_infix(), "((u ^ 2) + u) where u = (x + 1), v = 2");
+        // where applies to the whole expression, including assignments
+        let parsed = PrattParser::parse("a = u where u = 1")?;
+        assert_eq!(parsed.to_string(), "(where (= a u) (= u 1))");
+        let parsed = PrattParser::parse("2 * (u where u = 3)")?;
+        assert_eq!(parsed.to_string(), "(* 2 (where u (= u 3)))");
+
+        let err = PrattParser::parse("u where 3").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected a binding of the form name = value in where clause, found 3"
+        );
+        let err = PrattParser::parse("u where 2 = 3").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can only bind a variable in a where clause, not 2"
+        );
+        assert!(PrattParser::parse("u where").is_err());
+        assert!(PrattParser::parse("u where u = 1,").is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn test_floor_ceiling_parsing() -> Result<()> {
+        let parsed = PrattParser::parse("2 * ⌊x + 0.5⌋ - ⌈y⌉")?;
+        assert_eq!(parsed.to_string(), "(- (* 2 (⌊ (+ x 0.5))) (⌈ y))");
+        assert_eq!(parsed.to_infix(), "(2 * (⌊x + 0.5⌋)) - (⌈y⌉)");
+
+        let err = PrattParser::parse("⌊2.7").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unmatched ⌊ encountered during parsing, expected ⌋ but found EOF"
+        );
+        let err = PrattParser::parse("⌈2.7⌋").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unmatched ⌈ encountered during parsing, expected ⌉ but found ⌋"
+        );
+        assert!(PrattParser::parse("2.7⌋").is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn test_rpn() -> Result<()> {
+        let cases = [
+            ("3 + 4 * 2", "3 4 2 * +"),
+            ("-(1 - 2) + 3!", "1 2 - neg 3 ! +"),
+            ("x = +⌊y / 2⌋", "x y 2 / floor ="),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(PrattParser::parse(input)?.to_rpn()?, expected);
+        }
+        let err = PrattParser::parse("u where u = 1")?.to_rpn().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't write a where clause in reverse Polish notation"
+        );
+        assert_eq!(PrattParser::parse("max(x)")?.to_rpn()?, "x max");
+        let err = PrattParser::parse("max(1, 2)")?.to_rpn().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Can't write max with 2 arguments in reverse Polish notation"
+        );
+        return Ok(());
+    }
+
+    #[test]
+    fn test_comparison_precedence() -> Result<()> {
+        // From loosest to tightest: or, and, equality, relational, then
+        // arithmetic
+        let cases = [
+            ("1 + 1 == 2 and 3 < 4", "(&& (== (+ 1 1) 2) (< 3 4))"),
+            ("a < b == c > d", "(== (< a b) (> c d))"),
+            ("a != b <= c", "(!= a (<= b c))"),
+            ("a >= b != c", "(!= (>= a b) c)"),
+            ("a == b == c", "(== (== a b) c)"),
+            ("a < b < c", "(< (< a b) c)"),
+            (
+                "a or b and c == d < e + f",
+                "(|| a (&& b (== c (< d (+ e f)))))",
+            ),
+            ("a + b > c and d or e", "(|| (&& (> (+ a b) c) d) e)"),
+            ("not a < b == c", "(not (== (< a b) c))"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(PrattParser::parse(input)?.to_string(), expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_binding_powers() -> Result<()> {
+        let mut binding_powers = BindingPowers::default();
+        assert_eq!(
+            PrattParser::parse_with("2+3*4", &binding_powers)?.to_string(),
+            "(+ 2 (* 3 4))"
+        );
+        binding_powers.set_infix(Operator::Plus, 23, 24)?;
+        assert_eq!(
+            PrattParser::parse_with("2+3*4", &binding_powers)?.to_string(),
+            "(* (+ 2 3) 4)"
+        );
+        let err = binding_powers.set_infix(Operator::Bang, 1, 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown infix operator !, the infix operators are != % %= & && * *= + += - -= / // /= < << <= = == > >= >> ^ ^= | ||"
+        );
+        let err = binding_powers.set_infix(Operator::Star, 0, 2).unwrap_err();
+        assert_eq!(err.to_string(), "Binding powers must be at least 1");
+        return Ok(());
+    }
+
+    #[test]
+    fn test_assignment_style() -> Result<()> {
+        let mut binding_powers = BindingPowers::default();
+        assert_eq!(
+            PrattParser::parse_with("x = 5", &binding_powers)?.to_string(),
+            "(= x 5)"
+        );
+        assert!(PrattParser::parse_with("x := 5", &binding_powers).is_err());
+        binding_powers.set_assignment_style(AssignmentStyle::ColonEquals);
+        let cases = [
+            ("x := 5", "(= x 5)"),
+            ("x = 5", "(== x 5)"),
+            ("x := y = 5", "(= x (== y 5))"),
+            ("x += 1", "(+= x 1)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                PrattParser::parse_with(input, &binding_powers)?.to_string(),
+                expected
+            );
+        }
+        assert_eq!(
+            AssignmentStyle::from_name(":=")?,
+            AssignmentStyle::ColonEquals
+        );
+        assert!(AssignmentStyle::from_name("<-").is_err());
+        return Ok(());
+    }
+
+    #[test]
+    fn test_function_call_parsing() -> Result<()> {
+        let parsed = PrattParser::parse("2 * sqrt(x + 1) ^ 2")?;
+        assert_eq!(parsed.to_string(), "(^ (* 2 (sqrt (+ x 1))) 2)");
+        assert_eq!(parsed.to_infix(), "(2 * (sqrt(x + 1))) ^ 2");
+        let parsed = PrattParser::parse("f() + g(1, h(2), -3)")?;
+        assert_eq!(parsed.to_string(), "(+ (f) (g 1 (h 2) (- 3)))");
+        assert_eq!(parsed.to_infix(), "(f()) + (g(1, h(2), -3))");
+        assert_eq!(parsed.to_rpn()?, "f 1 2 h 3 neg g +");
+
+        let err = PrattParser::parse("sqrt(4⌋)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected , or ) after argument of sqrt, found ⌋"
+        );
+        assert!(PrattParser::parse("sqrt(1 2)").is_err());
+        // A trailing comma is allowed, but not an empty argument
+        let parsed = PrattParser::parse("g(1, 2,)")?;
+        assert_eq!(parsed.to_string(), "(g 1 2)");
+        let err = PrattParser::parse("g(1,,)").unwrap_err();
+        assert_eq!(err.to_string(), "Failed to parse argument of g");
+        assert!(PrattParser::parse("g(,)").is_err());
+        assert!(PrattParser::parse("sqrt(4").is_err());
+        return Ok(());
+    }
 }
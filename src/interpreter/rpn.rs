@@ -0,0 +1,185 @@
+//! Evaluation of input written in reverse Polish (postfix) notation
+//!
+//! RPN input is lexed as usual, but instead of going through the Pratt
+//! parser the tokens are assembled into an [`SExpr`] with a stack: atoms
+//! are pushed, and each operator pops its operands and pushes the
+//! combined expression. The names `neg`, `floor` and `ceil` stand for
+//! prefix minus, floor and ceiling, matching [`SExpr::to_rpn`], and the
+//! names of built in functions call them, so none of these can be used as
+//! variables in RPN input.
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use super::interpreter::{EvalResult, Interpreter, builtin_arity};
+use super::lexer::{AtomType, Keyword, Lexer, Operator, Token};
+use super::parser::{SExpr, SExprAtom};
+
+impl Interpreter {
+    /// Interpret a single expression written in reverse Polish notation,
+    /// e.g. `3 4 + 5 *`, returning its result like
+    /// [`Interpreter::interpret_full`]
+    pub fn interpret_rpn(&mut self, input: &str) -> Result<EvalResult> {
+        let expr = parse_rpn(input).context("Trying to parse reverse Polish notation input")?;
+        let value = self.interpret_sexpr(expr)?;
+        self.set_answer(value);
+        Ok(EvalResult::new(value, self.format_value(value), false))
+    }
+}
+
+/// Assemble reverse Polish notation input into an S-expression
+fn parse_rpn(input: &str) -> Result<SExpr> {
+    let tokens = Lexer::new(input)?.lex()?;
+    let mut stack: Vec<SExpr> = Vec::new();
+    for token in tokens {
+        let name = token.to_string();
+        let (op, arity) = match token {
+            Token::Atom(AtomType::Number(num)) => {
+                stack.push(SExpr::Atom(SExprAtom::Number(num)));
+                continue;
+            }
+            Token::Atom(AtomType::Integer(num)) => {
+                stack.push(SExpr::Atom(SExprAtom::Integer(num)));
+                continue;
+            }
+            Token::Atom(AtomType::Percent(num)) => {
+                stack.push(SExpr::percent(num));
+                continue;
+            }
+            Token::Atom(AtomType::Variable(varname)) => match varname.as_str() {
+                "neg" => (Operator::Minus, 1),
+                "floor" => (Operator::LFloor, 1),
+                "ceil" => (Operator::LCeil, 1),
+                name => {
+                    // Functions taking a varying number of arguments are
+                    // given the fewest they take
+                    let expr = match builtin_arity(name) {
+                        Some((arity, _)) => {
+                            if stack.len() < arity {
+                                return Err(anyhow!(
+                                    "Stack underflow, not enough operands on the stack for {name}"
+                                ));
+                            }
+                            let arguments = stack.split_off(stack.len() - arity);
+                            SExpr::Cons(SExprAtom::Variable(varname), arguments)
+                        }
+                        None => SExpr::Atom(SExprAtom::Variable(varname)),
+                    };
+                    stack.push(expr);
+                    continue;
+                }
+            },
+            Token::Op(Operator::Bang) => (Operator::Bang, 1),
+            Token::Op(Operator::Tilde) => (Operator::Tilde, 1),
+            // Written after the condition and both branches
+            Token::Op(Operator::Question) => (Operator::Question, 3),
+            Token::Keyword(Keyword::Not) => (Operator::Not, 1),
+            Token::Keyword(Keyword::And) => (Operator::And, 2),
+            Token::Keyword(Keyword::Or) => (Operator::Or, 2),
+            Token::Op(
+                op @ (Operator::Plus
+                | Operator::Minus
+                | Operator::Star
+                | Operator::Slash
+                | Operator::DoubleSlash
+                | Operator::Percent
+                | Operator::Caret
+                | Operator::Assign
+                | Operator::PlusAssign
+                | Operator::MinusAssign
+                | Operator::StarAssign
+                | Operator::SlashAssign
+                | Operator::PercentAssign
+                | Operator::CaretAssign
+                | Operator::Equal
+                | Operator::NotEqual
+                | Operator::Less
+                | Operator::LessEqual
+                | Operator::Greater
+                | Operator::GreaterEqual
+                | Operator::And
+                | Operator::Or
+                | Operator::BitAnd
+                | Operator::BitOr
+                | Operator::ShiftLeft
+                | Operator::ShiftRight),
+            ) => (op, 2),
+            Token::EOF => break,
+            t => {
+                return Err(anyhow!("{t} can't be used in reverse Polish notation"));
+            }
+        };
+        if stack.len() < arity {
+            return Err(anyhow!(
+                "Stack underflow, not enough operands on the stack for {name}"
+            ));
+        }
+        let operands = stack.split_off(stack.len() - arity);
+        stack.push(SExpr::Cons(SExprAtom::Op(op), operands));
+    }
+    match stack.len() {
+        0 => Err(anyhow!("Empty input, expected an expression")),
+        1 => Ok(stack.remove(0)),
+        n => Err(anyhow!(
+            "Expected a single value left on the stack, found {n}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test_rpn {
+    use super::*;
+    use crate::interpreter::parser::PrattParser;
+
+    #[test]
+    fn test_interpret_rpn() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let cases = [
+            ("3 4 + 5 *", 35f64),
+            ("2 3 ^ neg", -8f64),
+            ("4 ! 2 /", 12f64),
+            ("2.5 floor 2.5 ceil -", -1f64),
+            ("x 7 =", 7f64),
+            ("x 1 -", 6f64),
+            ("16 sqrt 1 +", 5f64),
+            ("1 2 <", 1f64),
+            ("200 10% +", 220f64),
+            ("0 1 2 ?", 2f64),
+            ("2 max", 2f64),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(test_interpreter.interpret_rpn(input)?.value, expected);
+        }
+        let value = test_interpreter.interpret_rpn("random 2 *")?.value;
+        assert!((0f64..2f64).contains(&value));
+        // RPN output reads back as the same expression
+        let parsed = PrattParser::parse("-(1 - x) * ⌊x / 2⌋ + 3!")?;
+        assert_eq!(
+            parse_rpn(&parsed.to_rpn()?)?.to_string(),
+            parsed.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rpn_errors() {
+        let cases = [
+            (
+                "3 +",
+                "Stack underflow, not enough operands on the stack for +",
+            ),
+            (
+                "neg",
+                "Stack underflow, not enough operands on the stack for neg",
+            ),
+            ("1 2", "Expected a single value left on the stack, found 2"),
+            ("", "Empty input, expected an expression"),
+            ("( 1 )", "( can't be used in reverse Polish notation"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_rpn(input).unwrap_err().to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,500 @@
+//! Conversion between expressions and spreadsheet formulas
+//!
+//! The spreadsheet dialect is a simple one: formulas may start with `=`,
+//! `=` compares rather than assigns, `<>` is not equal, function names are
+//! written in upper case (`SQRT`, `MAX`) with `,` or `;` separating their
+//! arguments, and a percent literal like `10%` is always 0.1, so
+//! `A+10%` adds 0.1 rather than ten percent of `A`. Logical operators and
+//! conditionals are the functions `AND`, `OR`, `NOT` and `IF`, factorials
+//! are `FACT`, and `pi` is `PI()`. Cell references such as `A1` have no
+//! meaning outside of a spreadsheet, so only named values can be converted.
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use super::interpreter::{builtin_arity, check_arity};
+use super::lexer::Operator;
+use super::parser::{PrattParser, SExpr, SExprAtom};
+
+/// Spreadsheet functions along with the built in functions they call
+const SPREADSHEET_FUNCTIONS: &[(&str, &str)] = &[
+    ("ABS", "abs"),
+    ("CEILING", "ceil"),
+    ("COS", "cos"),
+    ("EXP", "exp"),
+    ("FLOOR", "floor"),
+    ("LN", "ln"),
+    ("LOG10", "log10"),
+    ("MAX", "max"),
+    ("MIN", "min"),
+    ("RAND", "random"),
+    ("ROUND", "round"),
+    ("SIN", "sin"),
+    ("SQRT", "sqrt"),
+    ("TAN", "tan"),
+];
+
+impl SExpr {
+    /// Render the expression as a spreadsheet formula, e.g. `x != 2 * y`
+    /// as `=x<>(2*y)`, with every nested operation wrapped in parenthesis
+    ///
+    /// A percentage added to or subtracted from a value is relative to it
+    /// in the calculator but not in spreadsheets, so `x + 10%` is written
+    /// `=x*(1+10%)`. Assignments, where clauses, bitwise operators,
+    /// remainders and functions without a spreadsheet equivalent can't be
+    /// written, nor can variables named like cell references.
+    pub fn to_spreadsheet(&self) -> Result<String> {
+        Ok(format!("={}", self.to_spreadsheet_term()?))
+    }
+
+    /// Render the expression as part of a spreadsheet formula, without
+    /// the leading `=`
+    fn to_spreadsheet_term(&self) -> Result<String> {
+        match self {
+            SExpr::Atom(SExprAtom::Variable(name)) if name == "pi" => Ok("PI()".to_string()),
+            SExpr::Atom(SExprAtom::Variable(name)) if is_cell_reference(name) => Err(anyhow!(
+                "Can't write variable {name} in a spreadsheet formula, it would be read as a cell reference"
+            )),
+            SExpr::Atom(at) => Ok(at.to_string()),
+            SExpr::Cons(op, operands) => {
+                let terms = operands
+                    .iter()
+                    .map(SExpr::to_nested_spreadsheet)
+                    .collect::<Result<Vec<String>>>()?;
+                let arguments = || {
+                    operands
+                        .iter()
+                        .map(SExpr::to_spreadsheet_term)
+                        .collect::<Result<Vec<String>>>()
+                        .map(|arguments| arguments.join(","))
+                };
+                match (op, &terms[..]) {
+                    (SExprAtom::Op(Operator::Percent), [operand]) => Ok(format!("{operand}%")),
+                    (SExprAtom::Op(op @ (Operator::Plus | Operator::Minus)), [lhs, rhs])
+                        if operands[1].is_percent() =>
+                    {
+                        Ok(format!("{lhs}*(1{op}{rhs})"))
+                    }
+                    (
+                        SExprAtom::Op(
+                            op @ (Operator::Plus
+                            | Operator::Minus
+                            | Operator::Star
+                            | Operator::Slash
+                            | Operator::Caret
+                            | Operator::Less
+                            | Operator::LessEqual
+                            | Operator::Greater
+                            | Operator::GreaterEqual),
+                        ),
+                        [lhs, rhs],
+                    ) => Ok(format!("{lhs}{op}{rhs}")),
+                    (SExprAtom::Op(Operator::Equal), [lhs, rhs]) => Ok(format!("{lhs}={rhs}")),
+                    (SExprAtom::Op(Operator::NotEqual), [lhs, rhs]) => Ok(format!("{lhs}<>{rhs}")),
+                    (SExprAtom::Op(op @ (Operator::Plus | Operator::Minus)), [operand]) => {
+                        Ok(format!("{op}{operand}"))
+                    }
+                    (SExprAtom::Op(Operator::And), [_, _]) => Ok(format!("AND({})", arguments()?)),
+                    (SExprAtom::Op(Operator::Or), [_, _]) => Ok(format!("OR({})", arguments()?)),
+                    (SExprAtom::Op(Operator::Not), [_]) => Ok(format!("NOT({})", arguments()?)),
+                    (SExprAtom::Op(Operator::Question), [_, _, _]) => {
+                        Ok(format!("IF({})", arguments()?))
+                    }
+                    (SExprAtom::Op(Operator::Bang), [_]) => Ok(format!("FACT({})", arguments()?)),
+                    (SExprAtom::Op(Operator::LFloor), [_]) => {
+                        Ok(format!("FLOOR({})", arguments()?))
+                    }
+                    (SExprAtom::Op(Operator::LCeil), [_]) => {
+                        Ok(format!("CEILING({})", arguments()?))
+                    }
+                    // The natural log is LN, and LOG takes its base last
+                    (SExprAtom::Variable(name), [_]) if name == "log" => {
+                        Ok(format!("LN({})", arguments()?))
+                    }
+                    (SExprAtom::Variable(name), [_, _]) if name == "log" => Ok(format!(
+                        "LOG({},{})",
+                        operands[1].to_spreadsheet_term()?,
+                        operands[0].to_spreadsheet_term()?
+                    )),
+                    (SExprAtom::Variable(name), _) => {
+                        match SPREADSHEET_FUNCTIONS
+                            .iter()
+                            .find(|(_, builtin)| builtin == name)
+                        {
+                            Some((function, _)) => Ok(format!("{function}({})", arguments()?)),
+                            None => Err(anyhow!(
+                                "Can't write function {name} in a spreadsheet formula"
+                            )),
+                        }
+                    }
+                    (op, _) => Err(anyhow!("Can't write {op} in a spreadsheet formula")),
+                }
+            }
+            SExpr::Where(..) => Err(anyhow!(
+                "Can't write a where clause in a spreadsheet formula"
+            )),
+        }
+    }
+
+    /// Render the expression as an operand in a spreadsheet formula,
+    /// wrapping it in parenthesis unless it is an atom, a percentage or
+    /// written as a function
+    fn to_nested_spreadsheet(&self) -> Result<String> {
+        let term = self.to_spreadsheet_term()?;
+        let is_function = match self {
+            SExpr::Cons(SExprAtom::Variable(_), _) => true,
+            SExpr::Cons(SExprAtom::Op(op), _) => matches!(
+                op,
+                Operator::And
+                    | Operator::Or
+                    | Operator::Not
+                    | Operator::Question
+                    | Operator::Bang
+                    | Operator::LFloor
+                    | Operator::LCeil
+            ),
+            _ => false,
+        };
+        if matches!(self, SExpr::Atom(_)) || self.is_percent() || is_function {
+            Ok(term)
+        } else {
+            Ok(format!("({term})"))
+        }
+    }
+}
+
+/// Parse a spreadsheet formula, such as `=MAX(a;b)<>10%`, into an
+/// S-expression
+///
+/// Cell references such as `A1` or `$B$2` are rejected, as are strings
+/// and functions without a calculator equivalent. A percentage added to
+/// a value is 0.1, but `x*(1+10%)`, as written by
+/// [`SExpr::to_spreadsheet`], reads back as the relative `x + 10%`.
+pub fn parse_spreadsheet(formula: &str) -> Result<SExpr> {
+    let formula = formula.trim();
+    let formula = formula.strip_prefix('=').unwrap_or(formula);
+    let expr = PrattParser::parse(&translate_formula(formula)?)
+        .context("Failed to parse spreadsheet formula")?;
+    from_spreadsheet(expr, false)
+}
+
+/// Rewrite the text of a spreadsheet formula with the calculator's
+/// operators, leaving function names in upper case to be replaced once
+/// the formula is parsed
+fn translate_formula(formula: &str) -> Result<String> {
+    let chars = formula.chars().collect::<Vec<char>>();
+    let mut translated = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$'))
+                {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                // Functions such as LOG10 are named like cells
+                let is_call = chars[i..]
+                    .iter()
+                    .find(|c| !c.is_whitespace())
+                    .is_some_and(|c| *c == '(');
+                if !is_call && is_cell_reference(&word) {
+                    return Err(anyhow!(
+                        "Cell reference {word} can't be converted, only named values are supported"
+                    ));
+                }
+                if word.contains('$') {
+                    return Err(anyhow!("Unsupported character $ in spreadsheet formula"));
+                }
+                if is_call {
+                    translated.push_str(&word.to_uppercase());
+                } else {
+                    translated.push_str(&word);
+                }
+                continue;
+            }
+            // Taken whole, so an exponent isn't read as a cell reference
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    translated.push(chars[i]);
+                    i += 1;
+                }
+                if chars.get(i).is_some_and(|c| matches!(c, 'e' | 'E')) {
+                    translated.push('e');
+                    i += 1;
+                    if chars.get(i).is_some_and(|c| matches!(c, '+' | '-')) {
+                        translated.push(chars[i]);
+                        i += 1;
+                    }
+                }
+                continue;
+            }
+            '<' if chars.get(i + 1) == Some(&'>') => {
+                translated.push_str("!=");
+                i += 1;
+            }
+            '<' | '>' if chars.get(i + 1) == Some(&'=') => {
+                translated.push(c);
+                translated.push('=');
+                i += 1;
+            }
+            '=' => translated.push_str("=="),
+            ';' => translated.push(','),
+            // Spaced out, so the calculator doesn't read a remainder
+            '%' => translated.push_str("% "),
+            c if c.is_whitespace() || "+-*/^()<>,".contains(c) => translated.push(c),
+            c => return Err(anyhow!("Unsupported character {c} in spreadsheet formula")),
+        }
+        i += 1;
+    }
+    Ok(translated)
+}
+
+/// Replace the spreadsheet functions in a parsed formula with the
+/// calculator's operators and functions, added being whether expr is
+/// the right hand side of `+` or `-`
+fn from_spreadsheet(expr: SExpr, added: bool) -> Result<SExpr> {
+    // Relative percentages are written `x*(1+10%)`
+    if let SExpr::Cons(SExprAtom::Op(Operator::Star), operands) = &expr
+        && let [
+            lhs,
+            SExpr::Cons(SExprAtom::Op(op @ (Operator::Plus | Operator::Minus)), terms),
+        ] = &operands[..]
+        && let [one, percent] = &terms[..]
+        && is_one(one)
+        && percent.is_percent()
+    {
+        return Ok(SExpr::Cons(
+            SExprAtom::Op(*op),
+            vec![from_spreadsheet(lhs.clone(), false)?, percent.clone()],
+        ));
+    }
+    let (op, operands) = match expr {
+        // Percent literals aren't relative in spreadsheets
+        SExpr::Cons(SExprAtom::Op(Operator::Percent), operands) if added => {
+            return match &operands[..] {
+                [SExpr::Atom(SExprAtom::Number(n))] => {
+                    Ok(SExpr::Atom(SExprAtom::Number(n / 100f64)))
+                }
+                _ => Err(anyhow!("Expected a number before %")),
+            };
+        }
+        SExpr::Cons(op, operands) => (op, operands),
+        expr => return Ok(expr),
+    };
+    let is_sum =
+        matches!(op, SExprAtom::Op(Operator::Plus | Operator::Minus)) && operands.len() == 2;
+    let operands = operands
+        .into_iter()
+        .enumerate()
+        .map(|(i, operand)| from_spreadsheet(operand, is_sum && i == 1))
+        .collect::<Result<Vec<SExpr>>>()?;
+    let SExprAtom::Variable(function) = op else {
+        return Ok(SExpr::Cons(op, operands));
+    };
+    let operator = |operator: Operator, arity: usize, operands: Vec<SExpr>| {
+        check_arity(&function, arity, Some(arity), operands.len())?;
+        Ok(SExpr::Cons(SExprAtom::Op(operator), operands))
+    };
+    match function.as_str() {
+        "AND" => operator(Operator::And, 2, operands),
+        "OR" => operator(Operator::Or, 2, operands),
+        "NOT" => operator(Operator::Not, 1, operands),
+        "IF" => operator(Operator::Question, 3, operands),
+        "FACT" => operator(Operator::Bang, 1, operands),
+        "PI" => {
+            check_arity(&function, 0, Some(0), operands.len())?;
+            Ok(SExpr::Atom(SExprAtom::Variable("pi".to_string())))
+        }
+        // LOG defaults to base 10 and takes its base last
+        "LOG" => {
+            check_arity(&function, 1, Some(2), operands.len())?;
+            let mut operands = operands;
+            let name = if operands.len() == 1 { "log10" } else { "log" };
+            operands.reverse();
+            Ok(SExpr::Cons(SExprAtom::Variable(name.to_string()), operands))
+        }
+        _ => {
+            let Some((_, name)) = SPREADSHEET_FUNCTIONS
+                .iter()
+                .find(|(spreadsheet, _)| *spreadsheet == function)
+            else {
+                return Err(anyhow!("Unsupported spreadsheet function {function}"));
+            };
+            if let Some((min_arity, max_arity)) = builtin_arity(name) {
+                check_arity(&function, min_arity, max_arity, operands.len())?;
+            }
+            Ok(SExpr::Cons(SExprAtom::Variable(name.to_string()), operands))
+        }
+    }
+}
+
+/// Check whether an expression is the number one
+fn is_one(expr: &SExpr) -> bool {
+    matches!(expr, SExpr::Atom(SExprAtom::Integer(1)))
+        || matches!(expr, SExpr::Atom(SExprAtom::Number(n)) if *n == 1f64)
+}
+
+/// Check whether a name would be read as a cell reference, one to three
+/// letters followed by a row number, either of which may be made absolute
+/// with `$`
+fn is_cell_reference(name: &str) -> bool {
+    let name = name.strip_prefix('$').unwrap_or(name);
+    let letters = name.chars().take_while(char::is_ascii_alphabetic).count();
+    let row = name[letters..]
+        .strip_prefix('$')
+        .unwrap_or(&name[letters..]);
+    (1..=3).contains(&letters) && !row.is_empty() && row.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod test_spreadsheet {
+    use super::*;
+    use crate::interpreter::interpreter::Interpreter;
+
+    #[test]
+    fn test_to_spreadsheet() -> Result<()> {
+        let cases = [
+            ("x != 2 * y", "=x<>(2*y)"),
+            ("a == b", "=a=b"),
+            ("sqrt(x) + max(1, y, 3)", "=SQRT(x)+MAX(1,y,3)"),
+            ("log(x) - log(2, x)", "=LN(x)-LOG(x,2)"),
+            (
+                "x > 1 and not y ? 3! : pi",
+                "=IF(AND(x>1,NOT(y)),FACT(3),PI())",
+            ),
+            ("-⌊x / 2⌋ * ⌈y⌉", "=(-FLOOR(x/2))*CEILING(y)"),
+            ("rate * 3.5%", "=rate*3.5%"),
+            ("price + 10%", "=price*(1+10%)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(PrattParser::parse(input)?.to_spreadsheet()?, expected);
+        }
+        let errors = [
+            ("x = 2", "Can't write = in a spreadsheet formula"),
+            ("x & 3", "Can't write & in a spreadsheet formula"),
+            (
+                "ulp(x)",
+                "Can't write function ulp in a spreadsheet formula",
+            ),
+            (
+                "a1 + 2",
+                "Can't write variable a1 in a spreadsheet formula, it would be read as a cell reference",
+            ),
+            (
+                "u where u = 2",
+                "Can't write a where clause in a spreadsheet formula",
+            ),
+        ];
+        for (input, expected) in errors {
+            let err = PrattParser::parse(input)?.to_spreadsheet().unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_spreadsheet() -> Result<()> {
+        let cases = [
+            ("=MAX(a;b)<>10%", "(!= (max a b) (% 10))"),
+            ("=a>=b", "(>= a b)"),
+            ("= sqrt (x) ^ 2", "(^ (sqrt x) 2)"),
+            ("=LOG(1000)", "(log10 1000)"),
+            ("=LOG(8, 2)", "(log 2 8)"),
+            ("=IF(x=1,PI(),FACT(3))", "(? (== x 1) pi (! 3))"),
+            // Percentages aren't relative to what they're added to
+            ("=A+10%", "(+ A 0.1)"),
+            ("=10%-3", "(- (% 10) 3)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_spreadsheet(input)?.to_string(), expected);
+        }
+        let mut test_interpreter = Interpreter::new();
+        let value = test_interpreter.interpret_sexpr(parse_spreadsheet("=200+10%")?)?;
+        assert_eq!(value, 200.1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spreadsheet_errors() {
+        let errors = [
+            (
+                "=A1+2",
+                "Cell reference A1 can't be converted, only named values are supported",
+            ),
+            (
+                "=SUM($B$2, 3)",
+                "Cell reference $B$2 can't be converted, only named values are supported",
+            ),
+            (
+                "=ab12*2",
+                "Cell reference ab12 can't be converted, only named values are supported",
+            ),
+            (
+                "=VLOOKUP(x, y, 2)",
+                "Unsupported spreadsheet function VLOOKUP",
+            ),
+            (
+                "=\"total\"",
+                "Unsupported character \" in spreadsheet formula",
+            ),
+            (
+                "=NOT(a, b)",
+                "Function NOT takes 1 argument, but was given 2",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(parse_spreadsheet(input).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("rate = 0.25; total = 12; count = 5")?;
+        // Formulas read back as the same formula, with the same value
+        let formulas = [
+            "=(rate*total)-(count/2)",
+            "=SQRT(total)+MAX(1,count,3)",
+            "=IF(AND(total>10,NOT(count=4)),FACT(count),PI())",
+            "=LN(total)-LOG(total,2)",
+            "=(-FLOOR(total/5))*CEILING(rate)",
+            "=ROUND(total*3.5%,2)",
+            "=total<>count",
+        ];
+        for formula in formulas {
+            let expr = parse_spreadsheet(formula)?;
+            assert_eq!(expr.to_spreadsheet()?, formula);
+            let value = test_interpreter.interpret_sexpr(expr.clone())?;
+            let reparsed = parse_spreadsheet(&expr.to_spreadsheet()?)?;
+            assert_eq!(test_interpreter.interpret_sexpr(reparsed)?, value);
+        }
+        // Expressions written as formulas read back with the same value
+        let expressions = [
+            "rate * total - count / 2",
+            "total + 10%",
+            "count - 25% + 50%",
+            "log(total) + log10(count) + abs(-rate)",
+            "total > 10 or count < 2 ? ⌈rate⌉ : -1",
+            "min(total, count) ^ 2 != 25",
+        ];
+        for input in expressions {
+            let expr = PrattParser::parse(input)?;
+            let value = test_interpreter.interpret_sexpr(expr.clone())?;
+            let formula = expr.to_spreadsheet()?;
+            let reparsed = parse_spreadsheet(&formula)?;
+            assert_eq!(
+                test_interpreter.interpret_sexpr(reparsed.clone())?,
+                value,
+                "{input} written as {formula}"
+            );
+            assert_eq!(reparsed.to_spreadsheet()?, formula);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+//! A calculator built on a Pratt parser and a tree-walk interpreter
+//!
+//! Input is split into [`Token`]s by the [`Lexer`], parsed into an
+//! [`SExpr`] by the [`PrattParser`], and evaluated by the
+//! [`Interpreter`], which keeps the variables assigned along the way.
+//!
+//! ```
+//! use pratt_calculator::Interpreter;
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.interpret("x = 3").unwrap();
+//! assert_eq!(interpreter.interpret("x ^ 2 + 1").unwrap(), 10.0);
+//! ```
+//!
+//! The [`prelude`] re-exports the types most uses need.
+pub mod fsutil;
+pub mod interpreter;
+pub mod prelude;
+
+pub use interpreter::interpreter::Interpreter;
+pub use interpreter::lexer::{AtomType, Lexer, Operator, Token};
+pub use interpreter::parser::{PrattParser, SExpr, SExprAtom};
@@ -1,19 +1,40 @@
-pub(crate) mod interpreter;
+pub(crate) mod batch;
+pub(crate) mod calculus;
+pub(crate) mod compare;
+pub(crate) mod plot;
+pub(crate) mod repl;
+pub(crate) mod share;
+pub(crate) mod sweep;
+pub(crate) mod units;
 
 // Standard Library Uses
+use std::{env, fs, io};
 
 // External Uses
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 use rustyline::{self, DefaultEditor, error::ReadlineError};
 
 // Local Uses
-use crate::interpreter::interpreter::Interpreter;
+use crate::repl::Repl;
+use pratt_calculator::interpreter::builder::Preset;
+use pratt_calculator::interpreter::grammar;
+use pratt_calculator::interpreter::interpreter::Interpreter;
 
 fn main() -> Result<()> {
-    // Create the Tree-walk interpreter
-    let mut line_interpreter = Interpreter::new();
-    // Create the rustyline editor
-    let mut rl = DefaultEditor::new()?;
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if let Some(("--map-csv", rest)) = args.split_first().map(|(flag, rest)| (flag.as_str(), rest))
+    {
+        return run_map_csv(rest);
+    }
+    if let Some(("--check", rest)) = args.split_first().map(|(flag, rest)| (flag.as_str(), rest)) {
+        return run_check(rest);
+    }
+    if args.first().map(String::as_str) == Some("--dump-grammar") {
+        println!("{}", grammar::describe().to_json());
+        return Ok(());
+    }
+    // Create the REPL session wrapping the Tree-walk interpreter
+    let mut repl = Repl::new();
     // Print the welcome:
     print!(
         "
@@ -25,19 +46,92 @@ fn main() -> Result<()> {
                 - (subtraction or prefix),
                 * (multiplication)
                 / (division)
+                // (floor division)
+                % (modulo, or percent after a number, as in 3.5%,
+                  200 + 10% adds 10% of 200)
+                ‰ (permille after a number, as in 2‰)
                 ^ (exponentiation)
-            as well as paranenthesis, and simple variable assignment.
+                < > <= >= == != (comparisons, giving 1 or 0)
+                and or not (logical operators, also written && and ||)
+                & | ~ << >> (bitwise and, or, not and shifts of integers)
+                c ? a : b (a if c is nonzero, otherwise b)
+                ⌊x⌋ and ⌈x⌉ (floor and ceiling)
+                sqrt, sin, cos, tan, ln, log (natural, like ln, or
+                log(base, x) to any base), log10, abs, exp, ulp,
+                floor, ceil, round (ties away from zero, or
+                round_half_even and round_half_up, all taking an
+                optional number of digits as in round(x, 2)), max and
+                min of any number of arguments, and random() between
+                0 and 1
+                (functions, e.g. sqrt(16))
+            as well as paranenthesis, and simple variable assignment
+            (including updates such as x += 1). The variables pi, e,
+            tau and inf start out holding their constants.
+            Local values can be given with where, e.g.
+            u^2 + u where u = 3.
+            Units can be converted with convert <value> <from> <to>,
+            e.g. convert 100 km mi, and times to seconds with
+            convert H:M:S. M+ and M- add or subtract the last
+            result from memory, MR recalls it and MC clears it, and
+            ans is the last result. sumall and countall give the sum
+            and count of the results so far.
+            plot <expression> <lo> <hi> draws the expression as x
+            goes from lo to hi, diff <expression> x <point>
+            estimates its derivative at a point, and
+            integrate <expression> x <lo> <hi> its integral.
+            rpn <expression> writes an expression in reverse Polish
+            notation, and mode rpn switches to entering expressions
+            that way, e.g. 3 4 + 5 * (mode infix switches back).
+            setbp <operator> <left> <right> changes the binding
+            powers of an infix operator, to experiment with
+            precedence, and :assignment := switches to assigning
+            with := and comparing with = (:assignment = switches
+            back). :share gives a code for the last input,
+            which :open <code> (or pasting the code) evaluates.
+            :sweep <expression>, x, <lo>, <hi>, <step> tabulates
+            the expression as x goes from lo to hi.
+            :compare <expression> ; <expression> compares two
+            expressions with their free variables set to each value
+            of the grid, which :grid <values> changes. :vars lists
+            the variables and their values, and :reset clears them.
+            NaN and infinities aren't stored in variables unless
+            :quarantine off is used, and :vars marks any that are.
+            :lint int-div on warns when dividing whole numbers such
+            as 7 / 2 gives a fraction, in case // was meant.
+            Previous inputs can be listed with :history, or found
+            with search <text>, and re-run
+            with !N (or !! for the last input). End a line with \\ to
+            continue the input on the next line.
             Thank you for trying out Pratt Calculator! 
         "
     );
     println!("Version {}", env!("CARGO_PKG_VERSION"));
+    // Create the rustyline editor, falling back to reading plain lines
+    // when the terminal can't support it
+    let editor = if repl::supports_line_editing(env::var("TERM").ok().as_deref()) {
+        DefaultEditor::new()
+            .inspect_err(|err| eprintln!("Line editing unavailable: {err}"))
+            .ok()
+    } else {
+        None
+    };
+    match editor {
+        Some(rl) => run_editor(rl, &mut repl),
+        None => repl.run_plain(io::stdin().lock(), io::stdout()),
+    }
+}
+
+/// Run the REPL with a rustyline editor reading the input
+fn run_editor(mut rl: DefaultEditor, repl: &mut Repl) -> Result<()> {
     loop {
-        let readline = rl.readline(">>");
+        let readline = rl.readline(&repl.prompt());
         match readline {
-            Ok(line) => match line_interpreter.interpret(&line) {
-                Ok(output) => println!("{output}"),
-                Err(err) => println!("Interpreter Error: {err}"),
-            },
+            Ok(line) => {
+                rl.add_history_entry(line.as_str())?;
+                if let Some(output) = repl.feed_line(&line) {
+                    println!("{output}");
+                }
+            }
             Err(ReadlineError::Interrupted) => {
                 println!("Quitting...");
                 break;
@@ -54,3 +148,57 @@ fn main() -> Result<()> {
     }
     Ok(())
 }
+
+/// Run `--check --file <file>` or `--check <expr>`, parsing each line of
+/// the file, or the expression, without evaluating anything
+fn run_check(args: &[String]) -> Result<()> {
+    let interpreter = Interpreter::builder().preset(Preset::Embedded).build();
+    let (failures, total) = match args {
+        [flag, path] if flag == "--file" => {
+            let text =
+                fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+            let failures = batch::check_lines(&interpreter, &text);
+            for (line, err) in &failures {
+                eprintln!("line {line}: {err:#}");
+            }
+            let total = text.lines().filter(|line| !line.trim().is_empty()).count();
+            (failures.len(), total)
+        }
+        [flag, ..] if flag == "--file" => {
+            return Err(anyhow!("Expected --check --file <file>"));
+        }
+        [] => {
+            return Err(anyhow!(
+                "Expected --check --file <file> or --check <expression>"
+            ));
+        }
+        expr => {
+            let failed = match interpreter.check(&expr.join(" ")) {
+                Ok(()) => 0,
+                Err(err) => {
+                    eprintln!("{err:#}");
+                    1
+                }
+            };
+            (failed, 1)
+        }
+    };
+    match failures {
+        0 => Ok(()),
+        n => Err(anyhow!("{n} of {total} expressions failed to parse")),
+    }
+}
+
+/// Run `--map-csv <file> <expr>`, printing the value of expr for each
+/// row of the CSV file
+fn run_map_csv(args: &[String]) -> Result<()> {
+    let Some((path, expr)) = args.split_first().filter(|(_, expr)| !expr.is_empty()) else {
+        return Err(anyhow!("Expected --map-csv <file> <expression>"));
+    };
+    let csv = fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+    let mut interpreter = Interpreter::builder().preset(Preset::Embedded).build();
+    for result in batch::map_csv(&mut interpreter, &csv, &expr.join(" "))? {
+        println!("{result}");
+    }
+    Ok(())
+}
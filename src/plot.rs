@@ -0,0 +1,115 @@
+//! Plotting expressions as ASCII graphs
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use pratt_calculator::interpreter::interpreter::Interpreter;
+
+/// The variable swept over the range when plotting
+pub(crate) const PLOT_VARIABLE: &str = "x";
+
+/// Number of points sampled across the range, one per column of the plot
+pub(crate) const PLOT_WIDTH: usize = 60;
+
+/// Number of rows in the plot
+pub(crate) const PLOT_HEIGHT: usize = 15;
+
+/// Evaluate an expression at evenly spaced values of `x` between lo and
+/// hi (inclusive)
+pub(crate) fn sample(
+    interpreter: &mut Interpreter,
+    expr: &str,
+    lo: f64,
+    hi: f64,
+    count: usize,
+) -> Result<Vec<f64>> {
+    if count < 2 {
+        return Err(anyhow!("Need at least 2 points to plot, got {count}"));
+    }
+    (0..count)
+        .map(|i| {
+            let x = lo + (hi - lo) * (i as f64) / ((count - 1) as f64);
+            interpreter
+                .interpret_with(expr, &[(PLOT_VARIABLE, x)])
+                .with_context(|| format!("Failed to evaluate {expr} at {PLOT_VARIABLE} = {x}"))
+        })
+        .collect()
+}
+
+/// Render sampled values as an ASCII plot with the given number of rows,
+/// leaving gaps for non-finite values
+pub(crate) fn render(values: &[f64], lo: f64, hi: f64, height: usize) -> String {
+    let finite = values.iter().filter(|v| v.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, |a, &b| a.min(b));
+    let max = finite.fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+    if min > max {
+        return "Nothing to plot, no finite values in range".to_string();
+    }
+
+    // Work out which row every value falls in, flat functions go in the middle
+    let rows = values
+        .iter()
+        .map(|&v| match v.is_finite() {
+            true if max == min => Some(height / 2),
+            true => Some(((max - v) / (max - min) * (height - 1) as f64).round() as usize),
+            false => None,
+        })
+        .collect::<Vec<_>>();
+
+    let label_width = format!("{max}").len().max(format!("{min}").len());
+    let mut lines = Vec::with_capacity(height + 2);
+    for row in 0..height {
+        let label = match row {
+            0 => format!("{max}"),
+            r if r == height - 1 => format!("{min}"),
+            _ => String::new(),
+        };
+        let points = rows
+            .iter()
+            .map(|&r| if r == Some(row) { '*' } else { ' ' })
+            .collect::<String>();
+        lines.push(format!("{label:>label_width$} |{}", points.trim_end()));
+    }
+    lines.push(format!(
+        "{:>label_width$} +{}",
+        "",
+        "-".repeat(values.len())
+    ));
+    let lo_label = format!("{lo}");
+    lines.push(format!(
+        "{:>label_width$}  {lo_label}{hi:>width$}",
+        "",
+        width = values.len().saturating_sub(lo_label.len())
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test_plot {
+    use super::*;
+
+    #[test]
+    fn test_sample() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let values = sample(&mut test_interpreter, "x^2", 0f64, 4f64, 5)?;
+        assert_eq!(values, vec![0f64, 1f64, 4f64, 9f64, 16f64]);
+        let values = sample(&mut test_interpreter, "1/x", -1f64, 1f64, 3)?;
+        assert_eq!(values, vec![-1f64, f64::INFINITY, 1f64]);
+        assert!(sample(&mut test_interpreter, "x", 0f64, 1f64, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let plot = render(&[0f64, 1f64, 2f64], 0f64, 2f64, 3);
+        assert_eq!(plot, "2 |  *\n  | *\n0 |*\n  +---\n   0 2");
+        // Flat functions are drawn across the middle, NaN points are gaps
+        let plot = render(&[1f64, f64::NAN, 1f64], 0f64, 2f64, 3);
+        assert_eq!(plot, "1 |\n  |* *\n1 |\n  +---\n   0 2");
+        let plot = render(&[f64::NAN], 0f64, 1f64, 3);
+        assert_eq!(plot, "Nothing to plot, no finite values in range");
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+//! The types most users of the calculator need, for a single glob import
+//!
+//! ```
+//! use pratt_calculator::prelude::*;
+//!
+//! let mut interpreter = Interpreter::builder()
+//!     .preset(Preset::Embedded)
+//!     .number_format(NumberFormat::Humane)
+//!     .build();
+//! let result: EvalResult = interpreter.interpret_full("x = 1.5e6 * 2").unwrap();
+//! assert_eq!(result.value, 3e6);
+//! assert_eq!(result.display, "3 million");
+//!
+//! let parsed: SExpr = PrattParser::parse("1 + 2 * x").unwrap();
+//! assert_eq!(parsed.to_string(), "(+ 1 (* 2 x))");
+//! assert_eq!(interpreter.interpret_sexpr(parsed).unwrap(), 6000001.0);
+//! ```
+pub use crate::interpreter::builder::{InterpreterBuilder, Preset};
+pub use crate::interpreter::format::{NumberFormat, SpecialValues};
+pub use crate::interpreter::interpreter::{DivisionMode, EvalResult, Interpreter, RoundingMode};
+pub use crate::interpreter::parser::{AssignmentStyle, PrattParser, SExpr};
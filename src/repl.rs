@@ -0,0 +1,2022 @@
+//! The Read-Eval-Print loop driving the interpreter
+// Standard Library Uses
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use crate::calculus;
+use crate::compare;
+use crate::plot;
+use crate::share;
+use crate::sweep;
+use crate::units;
+use pratt_calculator::interpreter::builder::Preset;
+use pratt_calculator::interpreter::format::{NumberFormat, SpecialValues};
+use pratt_calculator::interpreter::interpreter::{
+    ANSWER, CONSTANTS, DivisionMode, Interpreter, MEMORY_RECALL,
+};
+use pratt_calculator::interpreter::lexer::{Keyword, Lexer, Operator, Token};
+use pratt_calculator::interpreter::parser::{AssignmentStyle, ParseError, PrattParser, SExpr};
+
+/// Maximum number of entries shown by an unfiltered `:history`
+const HISTORY_LIST_LIMIT: usize = 20;
+
+/// Maximum depth of macros playing other macros (or themselves)
+const MACRO_DEPTH_LIMIT: usize = 16;
+
+/// Default template of the prompt shown when waiting for a new input
+const DEFAULT_PROMPT: &str = "[{index}]>>";
+
+/// Default prompt shown when waiting for the rest of a continued input
+const DEFAULT_CONTINUATION_PROMPT: &str = "..";
+
+/// The parts of the REPL state which can be shown in the prompt
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ReplState {
+    /// The history number the next input will have, as used by `!N`
+    pub(crate) index: usize,
+    /// Whether the input so far was continued with a trailing `\`
+    pub(crate) continuing: bool,
+    /// The modes which aren't at their defaults, such as `rpn`
+    pub(crate) modes: Vec<String>,
+    /// Whether variables have been assigned since the session started or
+    /// they were last reset, so would be lost by quitting
+    pub(crate) dirty: bool,
+}
+
+/// Running sum and count of the results of a session
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Aggregates {
+    /// Sum of all the results so far
+    pub(crate) sum: f64,
+    /// Number of results so far
+    pub(crate) count: u64,
+}
+
+impl Aggregates {
+    /// Add a result to the aggregates, NaN and infinite results are left
+    /// out so one bad result doesn't spoil the sum
+    pub(crate) fn add(&mut self, value: f64) {
+        if value.is_finite() {
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+}
+
+/// How expressions entered at the REPL are written
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum InputMode {
+    /// Usual infix notation, e.g. `(3 + 4) * 5`
+    #[default]
+    Infix,
+    /// Reverse Polish notation, e.g. `3 4 + 5 *`
+    Rpn,
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputMode::Infix => write!(f, "infix"),
+            InputMode::Rpn => write!(f, "rpn"),
+        }
+    }
+}
+
+impl InputMode {
+    /// Look up an input mode by its name
+    pub(crate) fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "infix" => Ok(InputMode::Infix),
+            "rpn" => Ok(InputMode::Rpn),
+            _ => Err(anyhow!("Unknown input mode {name}, expected infix or rpn")),
+        }
+    }
+}
+
+/// State of a REPL session, wrapping the interpreter
+pub(crate) struct Repl {
+    /// The interpreter evaluating the expressions
+    interpreter: Interpreter,
+    /// Numbered mirror of the inputs entered this session
+    history: Vec<String>,
+    /// Input collected so far from lines continued with a trailing `\`
+    pending: Option<String>,
+    /// Template of the prompt shown when waiting for a new input
+    prompt_template: String,
+    /// Prompt shown while waiting for the rest of a continued input
+    continuation_prompt: String,
+    /// Largest difference for values to count as equal in `equiv`
+    tolerance: f64,
+    /// Values tried for each free variable by `:compare`
+    grid: Vec<f64>,
+    /// Whether to warn about dividing whole numbers with a fractional
+    /// result, turned on with `:lint int-div on`
+    lint_integer_division: bool,
+    /// Recorded macros, the input lines they replay by name
+    macros: BTreeMap<String, Vec<String>>,
+    /// Name and lines so far of the macro being recorded
+    recording: Option<(String, Vec<String>)>,
+    /// How many macros are currently being played, one inside the other
+    play_depth: usize,
+    /// Sum and count of the expression results this session
+    aggregates: Aggregates,
+    /// How expressions are written
+    input_mode: InputMode,
+}
+
+impl Repl {
+    /// Create a new REPL session with a fresh interpreter
+    pub(crate) fn new() -> Self {
+        Repl {
+            interpreter: Interpreter::builder().preset(Preset::Interactive).build(),
+            history: Vec::new(),
+            pending: None,
+            prompt_template: DEFAULT_PROMPT.to_string(),
+            continuation_prompt: DEFAULT_CONTINUATION_PROMPT.to_string(),
+            tolerance: compare::DEFAULT_TOLERANCE,
+            grid: compare::DEFAULT_GRID.to_vec(),
+            lint_integer_division: false,
+            macros: BTreeMap::new(),
+            recording: None,
+            play_depth: 0,
+            aggregates: Aggregates::default(),
+            input_mode: InputMode::default(),
+        }
+    }
+
+    /// The state shown by the prompt
+    pub(crate) fn state(&self) -> ReplState {
+        let mut modes = Vec::new();
+        if self.input_mode != InputMode::default() {
+            modes.push(self.input_mode.to_string());
+        }
+        if self.interpreter.division_mode() != DivisionMode::default() {
+            modes.push(self.interpreter.division_mode().to_string());
+        }
+        let assignment_style = self.interpreter.binding_powers().assignment_style();
+        if assignment_style != AssignmentStyle::default() {
+            modes.push(assignment_style.to_string());
+        }
+        let variables = self.interpreter.variables();
+        ReplState {
+            index: self.history.len() + 1,
+            continuing: self.pending.is_some(),
+            modes,
+            dirty: variables.len() != CONSTANTS.len()
+                || variables
+                    .iter()
+                    .any(|(name, value)| !CONSTANTS.contains(&(name.as_str(), *value))),
+        }
+    }
+
+    /// The prompt to show when reading the next line
+    pub(crate) fn prompt(&self) -> String {
+        let state = self.state();
+        if state.continuing {
+            return self.continuation_prompt.clone();
+        }
+        // The template was checked when it was set
+        render_prompt(&self.prompt_template, &state).unwrap_or_default()
+    }
+
+    /// Run the REPL over plain lines of input, for when line editing isn't
+    /// available, writing the prompts and outputs until the input ends
+    pub(crate) fn run_plain<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> Result<()> {
+        let mut lines = input.lines();
+        loop {
+            write!(output, "{}", self.prompt())?;
+            output.flush()?;
+            let Some(line) = lines.next() else {
+                writeln!(output, "Quitting...")?;
+                return Ok(());
+            };
+            if let Some(result) = self.feed_line(&line.context("Failed to read input")?) {
+                writeln!(output, "{result}")?;
+            }
+        }
+    }
+
+    /// Feed a line of input to the REPL, returning the text to display,
+    /// or None if there is nothing to display, either because the line
+    /// ends with `\` and more input is needed or the output was suppressed
+    pub(crate) fn feed_line(&mut self, line: &str) -> Option<String> {
+        let mut input = self.pending.take().unwrap_or_default();
+        if let Some(continued) = line.trim_end().strip_suffix('\\') {
+            input.push_str(continued);
+            input.push(' ');
+            self.pending = Some(input);
+            return None;
+        }
+        input.push_str(line);
+        Some(self.process_line(&input)).filter(|output| !output.is_empty())
+    }
+
+    /// Process a single line of input, returning the text to display
+    pub(crate) fn process_line(&mut self, line: &str) -> String {
+        // History expansion happens before anything else, and the
+        // expanded text is not itself expanded again
+        let (line, echo) = match self.expand_history(line) {
+            Ok(Some(expanded)) => (expanded, true),
+            Ok(None) => (line.to_string(), false),
+            Err(err) => return format!("History Error: {err}"),
+        };
+        self.record_line(&line);
+        let output = if let Some(command) = line.trim().strip_prefix(':') {
+            self.run_command(command)
+        } else if line.trim().starts_with(share::SHARE_PREFIX) {
+            match self.run_open(line.trim()) {
+                Ok(output) => output,
+                Err(err) => format!("Share Error: {err:#}"),
+            }
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "convert")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.run_convert(arguments) {
+                Ok(output) => {
+                    self.interpreter.set_answer(output);
+                    format!("{output}")
+                }
+                Err(err) => format!("Conversion Error: {err:#}"),
+            }
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "equiv")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.run_equiv(arguments) {
+                Ok(output) => format!("{output}"),
+                Err(err) => format!("Equivalence Error: {err:#}"),
+            }
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "diff")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.run_diff(arguments) {
+                Ok(output) => {
+                    self.interpreter.set_answer(output);
+                    format!("{output}")
+                }
+                Err(err) => format!("Derivative Error: {err:#}"),
+            }
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "integrate")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.run_integrate(arguments) {
+                Ok(output) => {
+                    self.interpreter.set_answer(output);
+                    format!("{output}")
+                }
+                Err(err) => format!("Integral Error: {err:#}"),
+            }
+        } else if let Some(expr) = self
+            .bare_command_arguments(&line, "rpn")
+            .filter(|expr| !expr.is_empty())
+        {
+            match PrattParser::parse_with(expr, self.interpreter.binding_powers())
+                .and_then(|parsed| parsed.to_rpn())
+            {
+                Ok(output) => output,
+                Err(err) => format!("RPN Error: {err:#}"),
+            }
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "setbp")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.set_binding_power(arguments) {
+                Ok(output) => output,
+                Err(err) => format!("Binding Power Error: {err:#}"),
+            }
+        } else if let Some(substring) = self
+            .bare_command_arguments(&line, "search")
+            .filter(|substring| !substring.is_empty())
+        {
+            self.search_history(substring.trim())
+        } else if let Some(arguments) = self
+            .bare_command_arguments(&line, "plot")
+            .filter(|arguments| !arguments.is_empty())
+        {
+            match self.run_plot(arguments) {
+                Ok(output) => output,
+                Err(err) => format!("Plot Error: {err:#}"),
+            }
+        } else if let Some(mode) = self.bare_command_arguments(&line, "divmode") {
+            match self.set_division_mode(mode) {
+                Ok(output) => output,
+                Err(err) => format!("Mode Error: {err:#}"),
+            }
+        } else if let Some(mode) = self.bare_command_arguments(&line, "mode") {
+            match self.set_input_mode(mode) {
+                Ok(output) => output,
+                Err(err) => format!("Mode Error: {err:#}"),
+            }
+        } else if line.trim() == "sumall" {
+            format!("{}", self.aggregates.sum)
+        } else if line.trim() == "countall" {
+            format!("{}", self.aggregates.count)
+        } else if let Some(output) = self.run_memory(line.trim()) {
+            match output {
+                Ok(output) => format!("M = {output}"),
+                Err(err) => format!("Memory Error: {err:#}"),
+            }
+        } else {
+            let result = match self.input_mode {
+                InputMode::Infix => self.interpreter.interpret_full(&line),
+                InputMode::Rpn => self.interpreter.interpret_rpn(&line),
+            };
+            match result {
+                Ok(output) => {
+                    self.aggregates.add(output.value);
+                    let mut lines = Vec::new();
+                    if !output.suppressed {
+                        lines.push(output.display);
+                    }
+                    if self.input_mode == InputMode::Infix {
+                        lines.extend(
+                            self.lint_warnings()
+                                .into_iter()
+                                .map(|warning| format!("Warning: {warning}")),
+                        );
+                    }
+                    lines.join("\n")
+                }
+                Err(err) => match ParseError::suggestion(&err) {
+                    Some(suggestion) => {
+                        format!("Interpreter Error: {err:#}\nSuggestion: {suggestion}")
+                    }
+                    None => format!("Interpreter Error: {err:#}"),
+                },
+            }
+        };
+        if !line.trim().is_empty() {
+            self.history.push(line.clone());
+        }
+
+        if echo && !output.is_empty() {
+            format!("{line}\n{output}")
+        } else if echo {
+            line
+        } else {
+            output
+        }
+    }
+
+    /// The arguments of a line running the bare-word command word, or None
+    /// if the line doesn't start with word. It is also None when word is
+    /// followed by an operator which can't start its arguments and the
+    /// line is a valid expression, as in `mode = 2`, so word is a variable
+    /// and the line should be evaluated instead. Once word is a defined
+    /// variable any valid expression is evaluated, so `mode - 1` works too,
+    /// while word on its own still runs the command.
+    fn bare_command_arguments<'a>(&self, line: &'a str, word: &str) -> Option<&'a str> {
+        let arguments = line.trim().strip_prefix(word)?;
+        if !(arguments.is_empty() || arguments.starts_with(char::is_whitespace)) {
+            return None;
+        }
+        let arguments = arguments.trim_start();
+        // Arguments which don't lex, such as some search text, can't be
+        // part of an expression, and the lexer doesn't know statements
+        let first_statement = arguments.split(';').next().unwrap_or_default();
+        let first_token = Lexer::new(first_statement)
+            .and_then(|mut lexer| lexer.lex())
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next());
+        let starts_expression = match first_token {
+            Some(Token::Op(
+                Operator::Plus
+                | Operator::Minus
+                | Operator::Tilde
+                | Operator::LParen
+                | Operator::LFloor
+                | Operator::LCeil,
+            )) => false,
+            Some(Token::Op(_)) => true,
+            Some(Token::Keyword(keyword)) => keyword != Keyword::Not,
+            _ => false,
+        };
+        let is_variable = !arguments.is_empty() && self.interpreter.is_defined(word);
+        if (starts_expression || is_variable) && self.interpreter.check(line).is_ok() {
+            return None;
+        }
+        Some(arguments)
+    }
+
+    /// Expand `!!` and `!N` history references, returning None if the
+    /// line is not a history reference
+    fn expand_history(&self, line: &str) -> Result<Option<String>> {
+        let reference = match line.trim().strip_prefix('!') {
+            Some(reference) => reference,
+            None => return Ok(None),
+        };
+        if reference == "!" {
+            return match self.history.last() {
+                Some(entry) => Ok(Some(entry.clone())),
+                None => Err(anyhow!("No previous input to repeat")),
+            };
+        }
+        match reference.parse::<usize>() {
+            Ok(index) => match index.checked_sub(1).and_then(|i| self.history.get(i)) {
+                Some(entry) => Ok(Some(entry.clone())),
+                None => Err(anyhow!("No history entry {index}")),
+            },
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Run a `:` prefixed REPL command
+    fn run_command(&mut self, command: &str) -> String {
+        match self.dispatch_command(command) {
+            Ok(output) => output,
+            Err(err) => format!("Command Error: {err:#}"),
+        }
+    }
+
+    /// Split a command into its name and arguments, and run it
+    fn dispatch_command(&mut self, command: &str) -> Result<String> {
+        let arguments = split_arguments(command)?;
+        let Some((name, arguments)) = arguments.split_first() else {
+            return Err(anyhow!("Expected a command name after :"));
+        };
+        match name.as_str() {
+            "anonymize" => self.run_anonymize(arguments),
+            "assignment" => self.set_assignment_style(optional_argument(arguments)?.unwrap_or("")),
+            "format" => self.set_format(optional_argument(arguments)?.unwrap_or("")),
+            "grid" => self.set_grid(arguments),
+            "lint" => self.set_lint(arguments),
+            "history" => Ok(self.list_history(optional_argument(arguments)?.unwrap_or(""))),
+            "macros" => Ok(self.list_macros()),
+            "open" => self.run_open(&arguments.join(" ")),
+            "play" => self.play_macro(optional_argument(arguments)?.unwrap_or("")),
+            "record" => self.start_recording(optional_argument(arguments)?.unwrap_or("")),
+            "reset" => {
+                self.interpreter.reset_variables();
+                Ok("Variables reset".to_string())
+            }
+            "stop" => self.stop_recording(),
+            "compare" => {
+                let expressions = arguments.join(" ");
+                let Some((old, new)) = compare::split_expressions(&expressions) else {
+                    return Err(anyhow!("Expected :compare <expression> ; <expression>"));
+                };
+                let comparison = compare::compare(&mut self.interpreter, old, new, &self.grid)?;
+                Ok(comparison.to_string())
+            }
+            "cost" => {
+                let expr = PrattParser::parse_with(
+                    &arguments.join(" "),
+                    self.interpreter.binding_powers(),
+                )
+                .context("Failed to parse expression to estimate the cost of")?;
+                Ok(expr.cost_estimate().to_string())
+            }
+            "quarantine" => self.set_quarantine(optional_argument(arguments)?.unwrap_or("")),
+            "prompt" => self.set_prompt_template(optional_argument(arguments)?.unwrap_or("")),
+            "prompt-continuation" => {
+                Ok(self.set_continuation_prompt(optional_argument(arguments)?.unwrap_or("")))
+            }
+            "share" => self.run_share(&arguments.join(" ")),
+            "specials" => self.set_special_values(optional_argument(arguments)?.unwrap_or("")),
+            "step" => self.run_step(&arguments.join(" ")),
+            "sweep" => self.run_sweep(&arguments.join(" ")),
+            "tolerance" => self.set_tolerance(optional_argument(arguments)?.unwrap_or("")),
+            "vars" => Ok(self.list_variables()),
+            _ => Err(anyhow!(
+                "Unknown command :{name}, available commands are :anonymize, :assignment, :compare, :cost, :format, :grid, :history, :lint, :macros, :open, :play, :prompt, :prompt-continuation, :quarantine, :record, :reset, :share, :specials, :step, :stop, :sweep, :tolerance, :vars"
+            )),
+        }
+    }
+
+    /// Add a line to the macro being recorded, if there is one
+    ///
+    /// Lines run by playing a macro aren't recorded (the `:play` is), nor
+    /// are the commands starting and stopping the recording.
+    fn record_line(&mut self, line: &str) {
+        let Some((_, lines)) = &mut self.recording else {
+            return;
+        };
+        let command = line.split_whitespace().next().unwrap_or("");
+        if self.play_depth == 0 && !matches!(command, "" | ":record" | ":stop") {
+            lines.push(line.to_string());
+        }
+    }
+
+    /// Start recording input lines into a macro
+    fn start_recording(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Err(anyhow!("Expected :record <name>"));
+        }
+        if let Some((recording, _)) = &self.recording {
+            return Err(anyhow!(
+                "Already recording macro {recording}, use :stop to finish it first"
+            ));
+        }
+        self.recording = Some((name.to_string(), Vec::new()));
+        Ok(format!("Recording macro {name}, use :stop to finish"))
+    }
+
+    /// Finish recording a macro, replacing any macro with the same name
+    fn stop_recording(&mut self) -> Result<String> {
+        let Some((name, lines)) = self.recording.take() else {
+            return Err(anyhow!("Not recording a macro"));
+        };
+        let output = format!("Recorded macro {name} ({} lines)", lines.len());
+        self.macros.insert(name, lines);
+        Ok(output)
+    }
+
+    /// Replay the lines of a macro, echoing each with its output
+    fn play_macro(&mut self, name: &str) -> Result<String> {
+        let Some(lines) = self.macros.get(name).cloned() else {
+            return Err(anyhow!("No macro named {name}"));
+        };
+        if self.play_depth >= MACRO_DEPTH_LIMIT {
+            return Err(anyhow!(
+                "Macro depth limit of {MACRO_DEPTH_LIMIT} reached while playing {name}"
+            ));
+        }
+        self.play_depth += 1;
+        let mut output = Vec::new();
+        for line in lines {
+            let line_output = self.process_line(&line);
+            output.push(line);
+            if !line_output.is_empty() {
+                output.push(line_output);
+            }
+        }
+        self.play_depth -= 1;
+        Ok(output.join("\n"))
+    }
+
+    /// List the recorded macros along with their lines
+    fn list_macros(&self) -> String {
+        if self.macros.is_empty() {
+            return "No macros recorded".to_string();
+        }
+        self.macros
+            .iter()
+            .map(|(name, lines)| {
+                let lines = lines
+                    .iter()
+                    .map(|line| format!("    {line}"))
+                    .collect::<Vec<String>>();
+                format!("{name}:\n{}", lines.join("\n"))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// List every variable with its value, sorted by name
+    ///
+    /// Non-finite values stored with quarantine off are marked, so they
+    /// stand out before they spread into other results. The constant
+    /// `inf` isn't marked.
+    fn list_variables(&self) -> String {
+        self.interpreter
+            .variables()
+            .iter()
+            .map(|(name, value)| {
+                let marker = if !value.is_finite() && !CONSTANTS.contains(&(name.as_str(), *value))
+                {
+                    " [non-finite]"
+                } else {
+                    ""
+                };
+                format!("{name} = {}{marker}", self.format_value(*value))
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Run an `:anonymize [--keep-numbers] [--show-mapping] <expr>`
+    /// command, renaming the variables of expr and bucketing its numbers
+    fn run_anonymize(&mut self, arguments: &[String]) -> Result<String> {
+        let mut keep_numbers = false;
+        let mut show_mapping = false;
+        let mut arguments = arguments;
+        while let Some((flag, rest)) = arguments.split_first() {
+            match flag.as_str() {
+                "--keep-numbers" => keep_numbers = true,
+                "--show-mapping" => show_mapping = true,
+                _ => break,
+            }
+            arguments = rest;
+        }
+        let expr = PrattParser::parse_with(&arguments.join(" "), self.interpreter.binding_powers())
+            .context("Failed to parse expression to anonymize")?;
+        let anonymized = expr.anonymize(keep_numbers);
+        let mut output = anonymized.expr.to_infix();
+        if show_mapping {
+            for (replacement, name) in anonymized.mapping {
+                output.push_str(&format!("\n{replacement} = {name}"));
+            }
+        }
+        Ok(output)
+    }
+
+    /// Run a `:share [expr]` command, encoding expr (or the last input)
+    /// along with the values of the variables it uses as a share code
+    fn run_share(&mut self, expr: &str) -> Result<String> {
+        let expr = match (expr, self.history.last()) {
+            ("", Some(last)) => last.clone(),
+            ("", None) => {
+                return Err(anyhow!(
+                    "Nothing to share, enter an expression or give one to :share"
+                ));
+            }
+            (expr, _) => expr.to_string(),
+        };
+        let parsed = PrattParser::parse_with(&expr, self.interpreter.binding_powers())
+            .context("Failed to parse expression to share")?;
+        let bindings = parsed
+            .variables()
+            .into_iter()
+            .filter(|name| name != MEMORY_RECALL && name != ANSWER)
+            .filter_map(|name| Some((name.clone(), self.interpreter.variable(&name)?)))
+            .collect::<Vec<(String, f64)>>();
+        Ok(share::encode(&parsed, &bindings))
+    }
+
+    /// Run an `:open <code>` command, assigning the variables stored in a
+    /// share code then evaluating its expression
+    fn run_open(&mut self, code: &str) -> Result<String> {
+        let shared = share::decode(code)?;
+        let mut lines = Vec::new();
+        for (name, value) in shared.bindings {
+            self.interpreter.set_variable(&name, value)?;
+            lines.push(format!("{name} = {}", self.format_value(value)));
+        }
+        lines.push(shared.expr.to_infix());
+        let value = self.interpreter.interpret_sexpr(shared.expr)?;
+        self.interpreter.set_answer(value);
+        lines.push(self.format_value(value));
+        Ok(lines.join("\n"))
+    }
+
+    /// Evaluate an expression step by step, listing each step taken
+    fn run_step(&mut self, expr: &str) -> Result<String> {
+        let mut session = self.interpreter.debug(expr)?;
+        let mut lines = Vec::new();
+        while let Some(step) = session.step()? {
+            lines.push(format!(
+                "{}: {} => {}",
+                lines.len() + 1,
+                step.current_node_infix,
+                step.partial_tree_infix
+            ));
+            for (varname, value) in step.env_changes {
+                lines.push(format!("   {varname} = {value}"));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Run a `convert <value> <from> <to>` unit conversion, or a
+    /// `convert <H:M:S> [unit]` conversion of a time, into seconds unless
+    /// another unit is given
+    fn run_convert(&mut self, arguments: &str) -> Result<f64> {
+        let arguments = arguments.split_whitespace().collect::<Vec<&str>>();
+        if let Some(seconds) = arguments.first().and_then(|time| units::parse_time(time)) {
+            return match arguments[1..] {
+                [] => seconds,
+                [to] => units::convert(seconds?, "s", to),
+                _ => Err(anyhow!("Expected convert <H:M:S> [unit]")),
+            };
+        }
+        let [value, from, to] = arguments[..] else {
+            return Err(anyhow!("Expected convert <value> <from unit> <to unit>"));
+        };
+        let value = self
+            .interpreter
+            .interpret(value)
+            .context("Failed to evaluate value to convert")?;
+        units::convert(value, from, to)
+    }
+
+    /// Run an `equiv <expr> ; <expr>` command, checking whether both
+    /// expressions are equal to within the tolerance
+    fn run_equiv(&mut self, arguments: &str) -> Result<bool> {
+        let Some((first, second)) = arguments.split_once(';') else {
+            return Err(anyhow!("Expected equiv <expression> ; <expression>"));
+        };
+        let first = self
+            .interpreter
+            .interpret(first)
+            .context("Failed to evaluate first expression")?;
+        let second = self
+            .interpreter
+            .interpret(second)
+            .context("Failed to evaluate second expression")?;
+        Ok(compare::equivalent(first, second, self.tolerance))
+    }
+
+    /// Run a `plot <expr> <lo> <hi>` command, plotting expr as x varies
+    /// from lo to hi
+    fn run_plot(&mut self, arguments: &str) -> Result<String> {
+        let arguments = split_arguments(arguments)?;
+        let [expr @ .., lo, hi] = &arguments[..] else {
+            return Err(anyhow!("Expected plot <expression> <lo> <hi>"));
+        };
+        if expr.is_empty() {
+            return Err(anyhow!("Expected plot <expression> <lo> <hi>"));
+        }
+        let lo = self
+            .interpreter
+            .interpret(lo)
+            .context("Failed to evaluate lower bound of plot")?;
+        let hi = self
+            .interpreter
+            .interpret(hi)
+            .context("Failed to evaluate upper bound of plot")?;
+        let values = plot::sample(
+            &mut self.interpreter,
+            &expr.join(" "),
+            lo,
+            hi,
+            plot::PLOT_WIDTH,
+        )?;
+        Ok(plot::render(&values, lo, hi, plot::PLOT_HEIGHT))
+    }
+
+    /// Run a `diff <expr> <variable> <point>` command, estimating the
+    /// derivative of expr with respect to variable at point
+    fn run_diff(&mut self, arguments: &str) -> Result<f64> {
+        let arguments = split_arguments(arguments)?;
+        let [expr @ .., variable, point] = &arguments[..] else {
+            return Err(anyhow!("Expected diff <expression> <variable> <point>"));
+        };
+        if expr.is_empty() {
+            return Err(anyhow!("Expected diff <expression> <variable> <point>"));
+        }
+        let point = self
+            .interpreter
+            .interpret(point)
+            .context("Failed to evaluate point to differentiate at")?;
+        calculus::derivative(&mut self.interpreter, &expr.join(" "), variable, point)
+    }
+
+    /// Run an `integrate <expr> <variable> <lo> <hi>` command, estimating
+    /// the integral of expr as variable goes from lo to hi
+    fn run_integrate(&mut self, arguments: &str) -> Result<f64> {
+        let arguments = split_arguments(arguments)?;
+        let [expr @ .., variable, lo, hi] = &arguments[..] else {
+            return Err(anyhow!(
+                "Expected integrate <expression> <variable> <lo> <hi>"
+            ));
+        };
+        if expr.is_empty() {
+            return Err(anyhow!(
+                "Expected integrate <expression> <variable> <lo> <hi>"
+            ));
+        }
+        let lo = self
+            .interpreter
+            .interpret(lo)
+            .context("Failed to evaluate lower bound of integral")?;
+        let hi = self
+            .interpreter
+            .interpret(hi)
+            .context("Failed to evaluate upper bound of integral")?;
+        calculus::integral(&mut self.interpreter, &expr.join(" "), variable, lo, hi)
+    }
+
+    /// Run a `:sweep <expr>, <variable>, <lo>, <hi>, <step>` command,
+    /// tabulating expr as variable goes from lo to hi in steps
+    fn run_sweep(&mut self, arguments: &str) -> Result<String> {
+        // Split from the right, so the expression may itself contain commas
+        let mut parts = arguments.rsplitn(5, ',').map(str::trim).collect::<Vec<_>>();
+        parts.reverse();
+        let [expr, variable, lo, hi, step] = parts[..] else {
+            return Err(anyhow!(
+                "Expected :sweep <expression>, <variable>, <lo>, <hi>, <step>"
+            ));
+        };
+        let mut bound = |name: &str, input: &str| {
+            self.interpreter
+                .interpret(input)
+                .with_context(|| format!("Failed to evaluate {name} of sweep"))
+        };
+        let lo = bound("start", lo)?;
+        let hi = bound("end", hi)?;
+        let step = bound("step", step)?;
+        let sweep = sweep::sweep(&mut self.interpreter, expr, variable, lo, hi, step)?;
+        Ok(sweep.render(|value| self.format_value(value)))
+    }
+
+    /// Run a calculator style memory command (`M+`, `M-` or `MC`),
+    /// returning None if the line is not a memory command
+    fn run_memory(&mut self, command: &str) -> Option<Result<f64>> {
+        let sign = match command {
+            "M+" => 1f64,
+            "M-" => -1f64,
+            "MC" => {
+                self.interpreter.memory_clear();
+                return Some(Ok(0f64));
+            }
+            _ => return None,
+        };
+        Some(match self.interpreter.answer() {
+            Some(result) => Ok(self.interpreter.memory_add(sign * result)),
+            None => Err(anyhow!("No previous result for {command} to use")),
+        })
+    }
+
+    /// Set the template of the prompt, or show it if no new template is given
+    fn set_prompt_template(&mut self, template: &str) -> Result<String> {
+        if template.is_empty() {
+            return Ok(format!("Prompt template is {}", self.prompt_template));
+        }
+        render_prompt(template, &self.state())?;
+        self.prompt_template = template.to_string();
+        Ok(format!("Prompt template set to {template}"))
+    }
+
+    /// Set the prompt shown while waiting for the rest of a continued input
+    fn set_continuation_prompt(&mut self, prompt: &str) -> String {
+        if prompt.is_empty() {
+            return format!("Continuation prompt is {}", self.continuation_prompt);
+        }
+        self.continuation_prompt = prompt.to_string();
+        format!("Continuation prompt set to {prompt}")
+    }
+
+    /// Set how results are written out, or show the current format if no
+    /// new one is given
+    fn set_format(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Ok(format!("Format is {}", self.interpreter.number_format()));
+        }
+        let format = NumberFormat::from_name(name)?;
+        self.interpreter.set_number_format(format);
+        Ok(format!("Format set to {format}"))
+    }
+
+    /// Set how NaN and infinite results are written, or show the current
+    /// style if no new one is given
+    fn set_special_values(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Ok(format!(
+                "Special values are {}",
+                self.interpreter.special_values()
+            ));
+        }
+        let special_values = SpecialValues::from_name(name)?;
+        self.interpreter.set_special_values(special_values);
+        Ok(format!("Special values set to {special_values}"))
+    }
+
+    /// Write out a result in the current format
+    fn format_value(&self, value: f64) -> String {
+        self.interpreter.format_value(value)
+    }
+
+    /// Set how `/` treats whole numbers, or show the current mode if no
+    /// new one is given
+    fn set_division_mode(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Ok(format!(
+                "Division mode is {}",
+                self.interpreter.division_mode()
+            ));
+        }
+        let mode = DivisionMode::from_name(name)?;
+        self.interpreter.set_division_mode(mode);
+        Ok(format!("Division mode set to {mode}"))
+    }
+
+    /// Run a `setbp <op> <left> <right>` command, changing the binding
+    /// powers of an infix operator
+    fn set_binding_power(&mut self, arguments: &str) -> Result<String> {
+        let arguments = split_arguments(arguments)?;
+        let [op, left, right] = &arguments[..] else {
+            return Err(anyhow!("Expected setbp <operator> <left> <right>"));
+        };
+        let Some(op) = Operator::from_symbol(op) else {
+            return Err(anyhow!("Unknown operator {op}"));
+        };
+        let parse_power = |power: &str| {
+            power.parse::<u8>().map_err(|_| {
+                anyhow!("Invalid binding power {power}, expected a whole number from 1 to 255")
+            })
+        };
+        let (left, right) = (parse_power(left)?, parse_power(right)?);
+        self.interpreter.set_binding_power(op, left, right)?;
+        Ok(format!("Binding powers of {op} set to {left} {right}"))
+    }
+
+    /// Set how expressions are written, or show it if no mode is given
+    fn set_input_mode(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Ok(format!("Input mode is {}", self.input_mode));
+        }
+        self.input_mode = InputMode::from_name(name)?;
+        Ok(format!("Input mode set to {}", self.input_mode))
+    }
+
+    /// Set which operator assigns to a variable, or show it if none is given
+    fn set_assignment_style(&mut self, name: &str) -> Result<String> {
+        if name.is_empty() {
+            return Ok(format!(
+                "Assignment operator is {}",
+                self.interpreter.binding_powers().assignment_style()
+            ));
+        }
+        let style = AssignmentStyle::from_name(name)?;
+        self.interpreter.set_assignment_style(style);
+        Ok(format!("Assignment operator set to {style}"))
+    }
+
+    /// Set whether NaN and infinite values are refused when assigned to
+    /// variables, or show the current setting if no new one is given
+    fn set_quarantine(&mut self, setting: &str) -> Result<String> {
+        let quarantine = match setting {
+            "" => {
+                return Ok(format!(
+                    "Quarantine of non-finite values is {}",
+                    if self.interpreter.quarantine_nonfinite() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+            }
+            "on" => true,
+            "off" => false,
+            _ => return Err(anyhow!("Expected :quarantine [on | off], got {setting}")),
+        };
+        self.interpreter.set_quarantine_nonfinite(quarantine);
+        Ok(format!("Quarantine of non-finite values set to {setting}"))
+    }
+
+    /// Set the tolerance used by `equiv`, or show it if no new value is given
+    fn set_tolerance(&mut self, tolerance: &str) -> Result<String> {
+        if tolerance.is_empty() {
+            return Ok(format!("Tolerance is {}", self.tolerance));
+        }
+        let value = self
+            .interpreter
+            .interpret(tolerance)
+            .context("Failed to evaluate tolerance")?;
+        if value.is_nan() || value < 0f64 {
+            return Err(anyhow!("Tolerance must be non-negative, got {value}"));
+        }
+        self.tolerance = value;
+        Ok(format!("Tolerance set to {value}"))
+    }
+
+    /// Set whether a lint is on, or show whether it is if no setting is
+    /// given, the only lint being `int-div`
+    fn set_lint(&mut self, arguments: &[String]) -> Result<String> {
+        let (lint, setting) = match arguments {
+            [] => ("int-div", ""),
+            [lint] => (lint.as_str(), ""),
+            [lint, setting] => (lint.as_str(), setting.as_str()),
+            _ => return Err(anyhow!("Expected :lint [int-div [on | off]]")),
+        };
+        if lint != "int-div" {
+            return Err(anyhow!(
+                "Unknown lint {lint}, the available lint is int-div"
+            ));
+        }
+        self.lint_integer_division = match setting {
+            "" => {
+                return Ok(format!(
+                    "Lint int-div is {}",
+                    if self.lint_integer_division {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+            }
+            "on" => true,
+            "off" => false,
+            _ => return Err(anyhow!("Expected :lint int-div [on | off], got {setting}")),
+        };
+        Ok(format!("Lint int-div set to {setting}"))
+    }
+
+    /// Warnings from the lints which are on about the last expression
+    /// evaluated
+    fn lint_warnings(&self) -> Vec<String> {
+        // Dividing whole numbers already truncates in integer mode
+        if !self.lint_integer_division || self.interpreter.division_mode() == DivisionMode::Integer
+        {
+            return Vec::new();
+        }
+        self.interpreter
+            .last_parse_tree()
+            .map(SExpr::integer_division_warnings)
+            .unwrap_or_default()
+    }
+
+    /// Set the values `:compare` tries for each free variable, or show
+    /// them if no new values are given
+    fn set_grid(&mut self, values: &[String]) -> Result<String> {
+        if values.is_empty() {
+            return Ok(format!("Grid is {}", join_values(&self.grid)));
+        }
+        let mut grid = Vec::new();
+        for value in values {
+            let value = self
+                .interpreter
+                .interpret(value)
+                .with_context(|| format!("Failed to evaluate grid value {value}"))?;
+            if !value.is_finite() {
+                return Err(anyhow!("Grid values must be finite, got {value}"));
+            }
+            grid.push(value);
+        }
+        self.grid = grid;
+        Ok(format!("Grid set to {}", join_values(&self.grid)))
+    }
+
+    /// Run a `search <substring>` command, listing the numbered history
+    /// entries containing substring
+    fn search_history(&self, substring: &str) -> String {
+        match self.list_history(substring) {
+            listing if listing.is_empty() => format!("No history entries contain {substring}"),
+            listing => listing,
+        }
+    }
+
+    /// List the numbered history entries containing pattern
+    fn list_history(&self, pattern: &str) -> String {
+        let matching = self
+            .history
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| pattern.is_empty() || entry.contains(pattern))
+            .map(|(i, entry)| format!("{:>4}  {entry}", i + 1))
+            .collect::<Vec<String>>();
+        let start = matching.len().saturating_sub(HISTORY_LIST_LIMIT);
+        matching[start..].join("\n")
+    }
+}
+
+/// Write out values separated by spaces
+fn join_values(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Split the arguments of a command on whitespace
+///
+/// Arguments containing whitespace can be wrapped in double quotes,
+/// inside of which `\"` is a literal quote and `\\` a literal backslash,
+/// any other backslash is kept as is. A quoted argument must be followed
+/// by whitespace or the end of the input, and `""` is an empty argument.
+fn split_arguments(input: &str) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut argument = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.peek() {
+                        Some(&escaped @ ('"' | '\\')) => {
+                            argument.push(escaped);
+                            chars.next();
+                        }
+                        _ => argument.push('\\'),
+                    },
+                    Some(c) => argument.push(c),
+                    None => return Err(anyhow!("Unterminated quoted argument \"{argument}")),
+                }
+            }
+            if let Some(c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                return Err(anyhow!(
+                    "Unexpected {c} after quoted argument \"{argument}\""
+                ));
+            }
+        } else {
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                argument.push(c);
+                chars.next();
+            }
+        }
+        arguments.push(argument);
+    }
+    Ok(arguments)
+}
+
+/// Whether a terminal of the given type (the `TERM` environment
+/// variable) supports line editing
+pub(crate) fn supports_line_editing(term: Option<&str>) -> bool {
+    term != Some("dumb")
+}
+
+/// Render a prompt template for the given state
+///
+/// `{index}` is replaced with the history number of the next input,
+/// `{mode}` with the modes which aren't at their defaults, `{dirty}` with
+/// `*` if there are variables which would be lost by quitting, and `{{`
+/// and `}}` are literal braces.
+fn render_prompt(template: &str, state: &ReplState) -> Result<String> {
+    let mut prompt = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        prompt.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("{{").or(rest.strip_prefix("}}")) {
+            prompt.push_str(&rest[..1]);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{index}") {
+            prompt.push_str(&state.index.to_string());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{mode}") {
+            prompt.push_str(&state.modes.join(" "));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{dirty}") {
+            if state.dirty {
+                prompt.push('*');
+            }
+            rest = after;
+        } else {
+            let placeholder = rest.split_inclusive('}').next().unwrap_or(rest);
+            return Err(anyhow!(
+                "Unknown prompt placeholder {placeholder}, the available placeholders are {{index}}, {{mode}} and {{dirty}}"
+            ));
+        }
+    }
+    prompt.push_str(rest);
+    Ok(prompt)
+}
+
+/// Get the single optional argument of a command
+fn optional_argument(arguments: &[String]) -> Result<Option<&str>> {
+    match arguments {
+        [] => Ok(None),
+        [argument] => Ok(Some(argument)),
+        _ => Err(anyhow!(
+            "Expected at most one argument, found {}, quote arguments containing spaces",
+            arguments.len()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test_repl {
+    use super::*;
+
+    #[test]
+    fn test_repeat_last() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("3+4"), "7");
+        assert_eq!(test_repl.process_line("!!"), "3+4\n7");
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_numbered() -> Result<()> {
+        let mut test_repl = Repl::new();
+        test_repl.process_line("a=2");
+        test_repl.process_line("a*5");
+        test_repl.process_line("a=3");
+        assert_eq!(test_repl.process_line("!2"), "a*5\n15");
+        assert_eq!(
+            test_repl.process_line("!9"),
+            "History Error: No history entry 9"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_expansion_does_not_recurse() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line("!!"),
+            "History Error: No previous input to repeat"
+        );
+        // 3!! is a repeated factorial, it must not be expanded when re-executed
+        assert_eq!(test_repl.process_line("3!!"), "720");
+        assert_eq!(test_repl.process_line("!1"), "3!!\n720");
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_listing() -> Result<()> {
+        let mut test_repl = Repl::new();
+        test_repl.process_line("1+1");
+        test_repl.process_line("x=4");
+        test_repl.process_line("x^2");
+        assert_eq!(
+            test_repl.process_line(":history"),
+            "   1  1+1\n   2  x=4\n   3  x^2"
+        );
+        assert_eq!(test_repl.process_line(":history x"), "   2  x=4\n   3  x^2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        for line in ["rate = 1.5", "hours = 8", "rate * hours", "2^10"] {
+            test_repl.process_line(line);
+        }
+        assert_eq!(
+            test_repl.process_line("search rate"),
+            "   1  rate = 1.5\n   3  rate * hours"
+        );
+        assert_eq!(test_repl.process_line("search ^"), "   4  2^10");
+        // Followed by an operator, search is a variable
+        assert_eq!(test_repl.process_line("search = 2"), "2");
+        assert_eq!(test_repl.process_line("search * rate"), "3");
+        assert_eq!(
+            test_repl.process_line("search tax"),
+            "No history entries contain tax"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("convert 1 km m"), "1000");
+        test_repl.process_line("d=2");
+        assert_eq!(test_repl.process_line("convert d kg g"), "2000");
+        assert_eq!(
+            test_repl.process_line("convert 1 km furlong"),
+            "Conversion Error: Unknown unit furlong"
+        );
+        assert_eq!(test_repl.process_line("convert 1:30:00"), "5400");
+        assert_eq!(test_repl.process_line("convert 1:30:00 min"), "90");
+        test_repl.process_line("M+");
+        assert_eq!(test_repl.process_line("MR"), "90");
+        // Followed by an operator, convert is a variable
+        assert_eq!(test_repl.process_line("convert = 2"), "2");
+        assert_eq!(test_repl.process_line("convert / 4"), "0.5");
+        Ok(())
+    }
+
+    #[test]
+    fn test_continuation_prompt() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.prompt(), "[1]>>");
+        assert_eq!(test_repl.feed_line("1 + \\"), None);
+        assert_eq!(test_repl.prompt(), "..");
+        assert_eq!(test_repl.feed_line("2 *\\"), None);
+        assert_eq!(test_repl.prompt(), "..");
+        assert_eq!(test_repl.feed_line("3"), Some("7".to_string()));
+        assert_eq!(test_repl.prompt(), "[2]>>");
+
+        test_repl.feed_line(":prompt-continuation ...");
+        assert_eq!(test_repl.feed_line("4 -\\"), None);
+        assert_eq!(test_repl.prompt(), "...");
+        assert_eq!(test_repl.feed_line("1"), Some("3".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_arguments() -> Result<()> {
+        assert_eq!(
+            split_arguments("load file.calc")?,
+            vec!["load", "file.calc"]
+        );
+        assert_eq!(
+            split_arguments("load \"my file.calc\"")?,
+            vec!["load", "my file.calc"]
+        );
+        assert_eq!(
+            split_arguments(r#"log "C:\\path\\out.txt""#)?,
+            vec!["log", r"C:\path\out.txt"]
+        );
+        assert_eq!(
+            split_arguments(r#"save "a \"quoted\" name" C:\tmp"#)?,
+            vec!["save", "a \"quoted\" name", r"C:\tmp"]
+        );
+        assert_eq!(split_arguments(r#"x "" y"#)?, vec!["x", "", "y"]);
+        assert_eq!(split_arguments("   ")?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_arguments_errors() -> Result<()> {
+        let err = split_arguments("load \"my file.calc").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unterminated quoted argument \"my file.calc"
+        );
+        let err = split_arguments(r#"load "file"s"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected s after quoted argument \"file\""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_arguments() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":prompt-continuation \"... \""),
+            "Continuation prompt set to ... "
+        );
+        assert_eq!(test_repl.prompt(), "[2]>>");
+        test_repl.feed_line("1+\\");
+        assert_eq!(test_repl.prompt(), "... ");
+        assert_eq!(
+            test_repl.process_line(":history \"a"),
+            "Command Error: Unterminated quoted argument \"a"
+        );
+        assert_eq!(
+            test_repl.process_line(":history a b"),
+            "Command Error: Expected at most one argument, found 2, quote arguments containing spaces"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_commands() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line("M+"),
+            "Memory Error: No previous result for M+ to use"
+        );
+        test_repl.process_line("5");
+        assert_eq!(test_repl.process_line("M+"), "M = 5");
+        test_repl.process_line("3");
+        assert_eq!(test_repl.process_line("M+"), "M = 8");
+        assert_eq!(test_repl.process_line("MR"), "8");
+        test_repl.process_line("2");
+        assert_eq!(test_repl.process_line("M-"), "M = 6");
+        assert_eq!(test_repl.process_line("MR * 2"), "12");
+        assert_eq!(test_repl.process_line("MC"), "M = 0");
+        assert_eq!(test_repl.process_line("MR"), "0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_suppression() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.feed_line("x = 5;"), None);
+        assert_eq!(test_repl.feed_line("x"), Some("5".to_string()));
+        assert_eq!(test_repl.feed_line("a = 1; a + 1"), Some("2".to_string()));
+        assert_eq!(test_repl.feed_line("a + 10;"), None);
+        assert_eq!(test_repl.process_line("M+"), "M = 11");
+        Ok(())
+    }
+
+    #[test]
+    fn test_plot_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        let plot = test_repl.process_line("plot x^2 0 3");
+        assert_eq!(plot.lines().count(), plot::PLOT_HEIGHT + 2);
+        assert!(plot.starts_with("9 |"));
+        assert_eq!(
+            test_repl.process_line("plot 0 3"),
+            "Plot Error: Expected plot <expression> <lo> <hi>"
+        );
+        assert!(test_repl.process_line("x").starts_with("Interpreter Error"));
+        // Followed by an operator, plot is a variable
+        assert_eq!(test_repl.process_line("plot = 2"), "2");
+        assert_eq!(test_repl.process_line("plot ^ 3"), "8");
+        assert_eq!(test_repl.process_line("plot - 1"), "1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":sweep 1 / (x - 1), x, 0, 2, 0.5"),
+            "  x | 1 / (x - 1)\n  0 | -1\n0.5 | -2\n  1 | [inf]\n1.5 | 2\n  2 | 1\n1 result was not finite"
+        );
+        assert_eq!(
+            test_repl.process_line(":sweep x, x, 0"),
+            "Command Error: Expected :sweep <expression>, <variable>, <lo>, <hi>, <step>"
+        );
+        assert!(test_repl.process_line("x").starts_with("Interpreter Error"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_suggestion_display() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line("2 3"),
+            "Interpreter Error: Trying to parse input into S-expression for interpretation: \
+            Encountered unknown token 3 during rhs parsing loop\n\
+            Suggestion: missing operator — did you mean `2 * 3`?"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cost_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":cost 2 * 3!"),
+            "atoms: 2\narithmetic: 1\ncalls: 0\nassignments: 0\nfactorials: 1 (3 iterations)\nrelative score: 4"
+        );
+        // The expression is parsed with the current assignment style
+        test_repl.process_line(":assignment :=");
+        assert_eq!(
+            test_repl.process_line(":cost x = 2"),
+            "atoms: 2\narithmetic: 1\ncalls: 0\nassignments: 0\nfactorials: 0 (0 iterations)\nrelative score: 1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":compare x*(x+1)/2 ; (x^2+x)/2"),
+            "compared at 10 points\nmax absolute difference: 0 at x = -100\n\
+            max relative difference: 0 at x = -100"
+        );
+        assert_eq!(
+            test_repl.process_line(":compare x + 1"),
+            "Command Error: Expected :compare <expression> ; <expression>"
+        );
+
+        assert_eq!(
+            test_repl.process_line(":grid"),
+            "Grid is -100 -10 -2.5 -1 0 0.5 1 3 10 1000"
+        );
+        assert_eq!(test_repl.process_line(":grid 1 2 2^2"), "Grid set to 1 2 4");
+        assert_eq!(
+            test_repl.process_line(":compare x^2 ; 2*x"),
+            "compared at 3 points\nmax absolute difference: 8 at x = 4\n\
+            max relative difference: 0.5 at x = 1"
+        );
+        assert_eq!(
+            test_repl.process_line(":grid 1 1/0"),
+            "Command Error: Grid values must be finite, got inf"
+        );
+        assert_eq!(test_repl.process_line(":grid"), "Grid is 1 2 4");
+        // | is bitwise or rather than separating the expressions
+        assert_eq!(
+            test_repl.process_line(":compare x | 1 ; 1 | x"),
+            "compared at 3 points\nmax absolute difference: 0 at x = 1\n\
+            max relative difference: 0 at x = 1"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":step a = 2 * 3 + 1"),
+            "1: 2 * 3 => a = (6 + 1)\n2: 6 + 1 => a = 7\n3: a = 7 => 7\n   a = 7"
+        );
+        assert_eq!(test_repl.process_line("a"), "7");
+        Ok(())
+    }
+
+    #[test]
+    fn test_equiv_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("equiv 0.1 + 0.2 ; 0.3"), "true");
+        assert_eq!(test_repl.process_line(":tolerance 0"), "Tolerance set to 0");
+        assert_eq!(test_repl.process_line("equiv 0.1 + 0.2 ; 0.3"), "false");
+        assert_eq!(test_repl.process_line("equiv 2 ^ 3 ; 8"), "true");
+        assert_eq!(test_repl.process_line(":tolerance"), "Tolerance is 0");
+        assert_eq!(
+            test_repl.process_line(":tolerance -1"),
+            "Command Error: Tolerance must be non-negative, got -1"
+        );
+        assert_eq!(
+            test_repl.process_line("equiv 1"),
+            "Equivalence Error: Expected equiv <expression> ; <expression>"
+        );
+        // Followed by an operator, equiv is a variable
+        assert_eq!(test_repl.process_line("equiv = 2"), "2");
+        assert_eq!(test_repl.process_line("equiv = 3; equiv + 1"), "4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_prompt() -> Result<()> {
+        let state = ReplState {
+            index: 5,
+            continuing: false,
+            modes: Vec::new(),
+            dirty: false,
+        };
+        assert_eq!(render_prompt(DEFAULT_PROMPT, &state)?, "[5]>>");
+        assert_eq!(render_prompt("calc {index}> ", &state)?, "calc 5> ");
+        assert_eq!(render_prompt("{{{index}}}", &state)?, "{5}");
+        assert_eq!(render_prompt(">>", &state)?, ">>");
+        assert_eq!(render_prompt("[{mode}]{dirty}>>", &state)?, "[]>>");
+        let state = ReplState {
+            index: 2,
+            continuing: false,
+            modes: vec!["rpn".to_string(), "int".to_string()],
+            dirty: true,
+        };
+        assert_eq!(render_prompt("[{mode}]{dirty}>>", &state)?, "[rpn int]*>>");
+        let err = render_prompt("{angle}>>", &state).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown prompt placeholder {angle}, the available placeholders are {index}, {mode} and {dirty}"
+        );
+        assert!(render_prompt("{index", &state).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompt_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":prompt \"calc {index}> \""),
+            "Prompt template set to calc {index}> "
+        );
+        assert_eq!(test_repl.prompt(), "calc 2> ");
+        test_repl.process_line("1+1");
+        assert_eq!(test_repl.prompt(), "calc 3> ");
+        assert_eq!(
+            test_repl.process_line(":prompt {unsaved}"),
+            "Command Error: Unknown prompt placeholder {unsaved}, the available placeholders are {index}, {mode} and {dirty}"
+        );
+        assert_eq!(
+            test_repl.process_line(":prompt"),
+            "Prompt template is calc {index}> "
+        );
+        test_repl.process_line(":prompt \"{mode}{dirty}> \"");
+        assert_eq!(test_repl.prompt(), "> ");
+        test_repl.process_line("divmode int");
+        test_repl.process_line("mode rpn");
+        assert_eq!(test_repl.prompt(), "rpn int> ");
+        test_repl.process_line("x 2 =");
+        assert_eq!(test_repl.prompt(), "rpn int*> ");
+        test_repl.process_line(":reset");
+        test_repl.process_line("mode infix");
+        assert_eq!(test_repl.prompt(), "int> ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("3200 * 1000"), "3200000");
+        assert_eq!(
+            test_repl.process_line(":format humane"),
+            "Format set to humane"
+        );
+        assert_eq!(test_repl.process_line("3200 * 1000"), "3.2 million");
+        assert_eq!(test_repl.process_line("ans + 1"), "3.2 million");
+        assert_eq!(test_repl.process_line("ans - 3200000"), "1");
+        assert_eq!(test_repl.process_line(":format"), "Format is humane");
+        assert_eq!(
+            test_repl.process_line(":format words"),
+            "Command Error: Unknown format words, available formats are plain, humane"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_macro_record_and_play() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.feed_line(":record grow"),
+            Some("Recording macro grow, use :stop to finish".to_string())
+        );
+        assert_eq!(test_repl.feed_line("x = 2"), Some("2".to_string()));
+        assert_eq!(test_repl.feed_line("x = x * 3"), Some("6".to_string()));
+        assert_eq!(
+            test_repl.feed_line(":stop"),
+            Some("Recorded macro grow (2 lines)".to_string())
+        );
+        assert_eq!(
+            test_repl.feed_line(":play grow"),
+            Some("x = 2\n2\nx = x * 3\n6".to_string())
+        );
+        test_repl.feed_line("x = 5");
+        // Lines are replayed as entered, through the normal evaluation
+        test_repl.feed_line(":record twice");
+        test_repl.feed_line(":play grow");
+        test_repl.feed_line("x + 1");
+        test_repl.feed_line(":stop");
+        assert_eq!(
+            test_repl.feed_line(":play twice"),
+            Some(":play grow\nx = 2\n2\nx = x * 3\n6\nx + 1\n7".to_string())
+        );
+        assert_eq!(
+            test_repl.feed_line(":macros"),
+            Some("grow:\n    x = 2\n    x = x * 3\ntwice:\n    :play grow\n    x + 1".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vars_and_reset() {
+        let mut test_repl = Repl::new();
+        test_repl.process_line("width = 4");
+        test_repl.process_line("area = width ^ 2");
+        assert_eq!(
+            test_repl.process_line(":vars"),
+            "area = 16\ne = 2.718281828459045\ninf = inf\npi = 3.141592653589793\n\
+            tau = 6.283185307179586\nwidth = 4"
+        );
+        assert_eq!(test_repl.process_line(":reset"), "Variables reset");
+        assert!(!test_repl.process_line(":vars").contains("width"));
+        assert_eq!(test_repl.process_line("pi > 3"), "1");
+        assert!(
+            test_repl
+                .process_line(":variables")
+                .starts_with("Command Error: Unknown command :variables, available commands are")
+        );
+    }
+
+    #[test]
+    fn test_quarantine_command() {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":quarantine"),
+            "Quarantine of non-finite values is on"
+        );
+        assert!(
+            test_repl
+                .process_line("bad = 0/0")
+                .starts_with("Interpreter Error: Refusing to store non-finite value NaN in bad")
+        );
+        assert_eq!(
+            test_repl.process_line(":quarantine off"),
+            "Quarantine of non-finite values set to off"
+        );
+        test_repl.process_line("bad = 0/0");
+        test_repl.process_line("big = -1/0");
+        test_repl.process_line("fine = 1");
+        // Stored non-finite values are marked, but the inf constant isn't
+        assert_eq!(
+            test_repl.process_line(":vars"),
+            "bad = NaN [non-finite]\nbig = -inf [non-finite]\ne = 2.718281828459045\n\
+            fine = 1\ninf = inf\npi = 3.141592653589793\ntau = 6.283185307179586"
+        );
+        assert_eq!(
+            test_repl.process_line(":quarantine maybe"),
+            "Command Error: Expected :quarantine [on | off], got maybe"
+        );
+    }
+
+    #[test]
+    fn test_lint_command() {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("7 / 2"), "3.5");
+        assert_eq!(test_repl.process_line(":lint"), "Lint int-div is off");
+        assert_eq!(
+            test_repl.process_line(":lint int-div on"),
+            "Lint int-div set to on"
+        );
+        assert_eq!(
+            test_repl.process_line("7 / 2"),
+            "3.5\nWarning: 7 / 2 is 3.5, use 7 // 2 for integer division or write 7.0 / 2 if a fraction is intended"
+        );
+        assert_eq!(
+            test_repl.process_line("x = 1; half = 1 / 2;"),
+            "Warning: 1 / 2 is 0.5, use 1 // 2 for integer division or write 1.0 / 2 if a fraction is intended"
+        );
+        assert_eq!(test_repl.process_line("7.0 / 2"), "3.5");
+        assert_eq!(test_repl.process_line("3 * (7 / 2)"), "10.5");
+        // Whole numbers are already divided as integers in integer mode
+        test_repl.process_line("divmode int");
+        assert_eq!(test_repl.process_line("7 / 2"), "3");
+        test_repl.process_line("divmode float");
+        test_repl.process_line(":lint int-div off");
+        assert_eq!(test_repl.process_line("7 / 2"), "3.5");
+        assert_eq!(
+            test_repl.process_line(":lint float-eq on"),
+            "Command Error: Unknown lint float-eq, the available lint is int-div"
+        );
+    }
+
+    #[test]
+    fn test_macro_errors() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line(":macros"), "No macros recorded");
+        assert_eq!(
+            test_repl.process_line(":stop"),
+            "Command Error: Not recording a macro"
+        );
+        assert_eq!(
+            test_repl.process_line(":play missing"),
+            "Command Error: No macro named missing"
+        );
+        test_repl.process_line(":record loop");
+        assert_eq!(
+            test_repl.process_line(":record other"),
+            "Command Error: Already recording macro loop, use :stop to finish it first"
+        );
+        test_repl.process_line(":play loop");
+        test_repl.process_line(":stop");
+        // A macro playing itself stops at the depth limit
+        let output = test_repl.process_line(":play loop");
+        assert_eq!(output.lines().count(), MACRO_DEPTH_LIMIT + 1);
+        assert!(
+            output.ends_with("Command Error: Macro depth limit of 16 reached while playing loop")
+        );
+        // and the REPL is usable afterwards
+        assert_eq!(test_repl.process_line("1 + 1"), "2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregates() -> Result<()> {
+        let mut aggregates = Aggregates::default();
+        for value in [1.5, 2.5, -1.0, f64::NAN, f64::INFINITY] {
+            aggregates.add(value);
+        }
+        assert_eq!(
+            aggregates,
+            Aggregates {
+                sum: 3f64,
+                count: 3
+            }
+        );
+
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("sumall"), "0");
+        test_repl.process_line("x = 4");
+        test_repl.process_line("x * 2;");
+        test_repl.process_line("1 / 0");
+        test_repl.process_line("undefined + 1");
+        test_repl.process_line(":history");
+        assert_eq!(test_repl.process_line("sumall"), "12");
+        assert_eq!(test_repl.process_line("countall"), "2");
+        // Asking for the aggregates doesn't change them
+        assert_eq!(test_repl.process_line("countall"), "2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_divmode_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("divmode"), "Division mode is float");
+        assert_eq!(test_repl.process_line("7/2"), "3.5");
+        assert_eq!(
+            test_repl.process_line("divmode int"),
+            "Division mode set to int"
+        );
+        assert_eq!(test_repl.process_line("7/2"), "3");
+        assert_eq!(test_repl.process_line("7.5/2"), "3.75");
+        assert_eq!(
+            test_repl.process_line("divmode floor"),
+            "Mode Error: Unknown division mode floor, expected int or float"
+        );
+        test_repl.process_line("divmode float");
+        assert_eq!(test_repl.process_line("7/2"), "3.5");
+        assert_eq!(test_repl.process_line("divmodes = 2"), "2");
+        assert_eq!(test_repl.process_line("divmode = 2"), "2");
+        assert_eq!(test_repl.process_line("divmode += 1"), "3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_assignment_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":assignment"),
+            "Assignment operator is ="
+        );
+        assert_eq!(
+            test_repl.process_line(":assignment :="),
+            "Assignment operator set to :="
+        );
+        assert_eq!(test_repl.process_line("x := 5"), "5");
+        assert_eq!(test_repl.process_line("x = 5"), "1");
+        assert_eq!(test_repl.process_line("x = 6"), "0");
+        assert_eq!(
+            test_repl.process_line(":assignment <-"),
+            "Command Error: Unknown assignment operator <-, expected = or :="
+        );
+        test_repl.process_line(":assignment =");
+        assert_eq!(test_repl.process_line("x = 6"), "6");
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymize_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(
+            test_repl.process_line(":anonymize price * 1.175 + price"),
+            "(v1 * 1) + v1"
+        );
+        assert_eq!(
+            test_repl.process_line(":anonymize --keep-numbers --show-mapping price * 1.175 + tax"),
+            "(v1 * 1.175) + v2\nv1 = price\nv2 = tax"
+        );
+        // The expression is parsed with the current binding powers
+        test_repl.process_line("setbp + 23 24");
+        assert_eq!(
+            test_repl.process_line(":anonymize price * 2 + tax"),
+            "v1 * (2 + v2)"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        let output = test_repl.process_line("diff x^2 x 3").parse::<f64>()?;
+        assert!((output - 6f64).abs() < 1e-8);
+        test_repl.process_line("p = 2");
+        let output = test_repl
+            .process_line("diff \"y ^ 3\" y p")
+            .parse::<f64>()?;
+        assert!((output - 12f64).abs() < 1e-8);
+        assert_eq!(
+            test_repl.process_line("diff x 3"),
+            "Derivative Error: Expected diff <expression> <variable> <point>"
+        );
+        // Followed by an operator, diff is a variable
+        assert_eq!(test_repl.process_line("diff = 2"), "2");
+        assert_eq!(test_repl.process_line("diff * 3"), "6");
+        assert_eq!(test_repl.process_line("diff - 1"), "1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_integrate_command() -> Result<()> {
+        let mut test_repl = Repl::new();
+        let output = test_repl.process_line("integrate x x 0 2").parse::<f64>()?;
+        assert!((output - 2f64).abs() < 1e-9);
+        let output = test_repl
+            .process_line("integrate 1 / x x 1 3")
+            .parse::<f64>()?;
+        assert!((output - 3f64.ln()).abs() < 1e-9);
+        assert_eq!(
+            test_repl.process_line("integrate x 0 2"),
+            "Integral Error: Expected integrate <expression> <variable> <lo> <hi>"
+        );
+        // Followed by an operator, integrate is a variable
+        assert_eq!(test_repl.process_line("integrate = 2"), "2");
+        assert_eq!(test_repl.process_line("integrate ^ 2"), "4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rpn_command() {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("rpn 3 + 4 * 2"), "3 4 2 * +");
+        assert_eq!(test_repl.process_line("rpn -x!"), "x ! neg");
+        assert!(test_repl.process_line("rpn 3 +").starts_with("RPN Error: "));
+        // Followed by an operator, rpn is a variable
+        assert_eq!(test_repl.process_line("rpn = 2"), "2");
+        assert_eq!(test_repl.process_line("rpn == 2"), "1");
+    }
+
+    #[test]
+    fn test_rpn_mode() -> Result<()> {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("mode"), "Input mode is infix");
+        assert_eq!(test_repl.process_line("mode rpn"), "Input mode set to rpn");
+        assert_eq!(test_repl.process_line("3 4 + 5 *"), "35");
+        assert_eq!(test_repl.process_line("ans 2 / neg"), "-17.5");
+        assert!(
+            test_repl
+                .process_line("3 +")
+                .starts_with("Interpreter Error: ")
+        );
+        assert_eq!(
+            test_repl.process_line("mode prefix"),
+            "Mode Error: Unknown input mode prefix, expected infix or rpn"
+        );
+        test_repl.process_line("mode infix");
+        assert_eq!(test_repl.process_line("(3 + 4) * 5"), "35");
+        assert_eq!(test_repl.process_line("modest = 2"), "2");
+        // Followed by an operator, mode is a variable
+        assert_eq!(test_repl.process_line("mode = 2"), "2");
+        assert_eq!(test_repl.process_line("mode * 3"), "6");
+        // and once defined, even when followed by a prefix operator
+        assert_eq!(test_repl.process_line("mode - 1"), "1");
+        assert_eq!(test_repl.process_line("mode +1"), "3");
+        assert_eq!(test_repl.process_line("mode rpn"), "Input mode set to rpn");
+        test_repl.process_line("mode infix");
+        assert_eq!(test_repl.process_line("mode"), "Input mode is infix");
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_plain() -> Result<()> {
+        let mut test_repl = Repl::new();
+        let mut output = Vec::new();
+        test_repl.run_plain("x = 2\nx * \\\n3\n".as_bytes(), &mut output)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "[1]>>2\n[2]>>..6\n[3]>>Quitting...\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_supports_line_editing() {
+        assert!(supports_line_editing(Some("xterm-256color")));
+        assert!(supports_line_editing(None));
+        assert!(!supports_line_editing(Some("dumb")));
+    }
+
+    #[test]
+    fn test_setbp_command() {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("2+3*4"), "14");
+        assert_eq!(
+            test_repl.process_line("setbp + 23 24"),
+            "Binding powers of + set to 23 24"
+        );
+        assert_eq!(test_repl.process_line("2+3*4"), "20");
+        assert_eq!(test_repl.process_line("rpn 2+3*4"), "2 3 + 4 *");
+        assert_eq!(
+            test_repl.process_line("setbp + 300 1"),
+            "Binding Power Error: Invalid binding power 300, expected a whole number from 1 to 255"
+        );
+        assert_eq!(
+            test_repl.process_line("setbp ** 1 2"),
+            "Binding Power Error: Unknown operator **"
+        );
+        assert!(
+            test_repl
+                .process_line("setbp ! 1 2")
+                .starts_with("Binding Power Error: Unknown infix operator !")
+        );
+        // Followed by an operator, setbp is a variable unless the line
+        // isn't an expression
+        assert_eq!(test_repl.process_line("setbp = 2"), "2");
+        assert_eq!(test_repl.process_line("setbp * 3"), "6");
+        assert_eq!(
+            test_repl.process_line("setbp * 21 22"),
+            "Binding powers of * set to 21 22"
+        );
+    }
+
+    #[test]
+    fn test_share_and_open() {
+        let mut test_repl = Repl::new();
+        test_repl.process_line("rate = 0.5");
+        test_repl.process_line("unused = 3");
+        test_repl.process_line("rate * hours where hours = 8");
+        let code = test_repl.process_line(":share");
+        assert!(code.starts_with("pc1:"));
+        let explicit = test_repl.process_line(":share rate * hours where hours = 8");
+        assert_eq!(explicit, code);
+
+        // Only the variables the expression uses are carried along
+        let mut other_repl = Repl::new();
+        assert_eq!(
+            other_repl.process_line(&code),
+            "rate = 0.5\n(rate * hours) where hours = 8\n4"
+        );
+        assert_eq!(
+            other_repl.process_line("unused"),
+            "Interpreter Error: Tried to access variable with no value assigned"
+        );
+        assert_eq!(
+            other_repl.process_line(&format!(":open {code}")),
+            "rate = 0.5\n(rate * hours) where hours = 8\n4"
+        );
+        assert_eq!(other_repl.process_line("ans * 2"), "8");
+
+        assert_eq!(
+            other_repl.process_line(&code[..code.len() - 4]),
+            "Share Error: Invalid share code, it ends too early"
+        );
+        assert_eq!(
+            Repl::new().process_line(":share"),
+            "Command Error: Nothing to share, enter an expression or give one to :share"
+        );
+    }
+
+    #[test]
+    fn test_specials_command() {
+        let mut test_repl = Repl::new();
+        assert_eq!(test_repl.process_line("0/0"), "NaN");
+        assert_eq!(
+            test_repl.process_line(":specials readable"),
+            "Special values set to readable"
+        );
+        assert_eq!(test_repl.process_line("0/0"), "undefined");
+        assert_eq!(test_repl.process_line("1/0"), "∞");
+        assert_eq!(test_repl.process_line("-1/0"), "-∞");
+        assert_eq!(test_repl.process_line("1/4"), "0.25");
+        assert_eq!(
+            test_repl.process_line(":specials"),
+            "Special values are readable"
+        );
+        assert_eq!(
+            test_repl.process_line(":specials words"),
+            "Command Error: Unknown special value style words, expected plain or readable"
+        );
+        test_repl.process_line(":specials plain");
+        assert_eq!(test_repl.process_line("1/0"), "inf");
+    }
+}
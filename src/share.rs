@@ -0,0 +1,378 @@
+//! Compact share codes for passing expressions between sessions
+//!
+//! A share code is `pc1:` followed by the URL safe base64 encoding (without
+//! padding) of a small binary layout:
+//!
+//! - a version byte, currently 2
+//! - the expression, written node by node in prefix order, each starting
+//!   with a tag byte
+//! - the number of variable bindings, then each name and value
+//!
+//! Numbers are little endian, and strings (including operators, written
+//! as their symbol) are a `u32` byte length followed by their UTF-8 bytes.
+//! Version 1 codes, from before operators could be longer than one
+//! character, wrote each operator as a `u32` character instead, and can
+//! still be read.
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Result, anyhow};
+
+// Local Uses
+use pratt_calculator::interpreter::lexer::Operator;
+use pratt_calculator::interpreter::parser::{SExpr, SExprAtom};
+
+/// Prefix marking text as a share code
+pub(crate) const SHARE_PREFIX: &str = "pc1:";
+
+/// Version of the binary layout written by [`encode`]
+const SHARE_VERSION: u8 = 2;
+
+/// Oldest version of the binary layout which can still be decoded
+const OLDEST_SHARE_VERSION: u8 = 1;
+
+/// Alphabet of URL safe base64
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Tags starting each node of an encoded expression
+const TAG_NUMBER: u8 = 0;
+const TAG_INTEGER: u8 = 1;
+const TAG_VARIABLE: u8 = 2;
+const TAG_OP: u8 = 3;
+const TAG_CONS: u8 = 4;
+const TAG_WHERE: u8 = 5;
+
+/// An expression along with the values of the variables it needs
+#[derive(Clone, Debug)]
+pub(crate) struct Shared {
+    /// The shared expression
+    pub(crate) expr: SExpr,
+    /// Variables to assign before evaluating the expression
+    pub(crate) bindings: Vec<(String, f64)>,
+}
+
+/// Encode an expression and variable bindings as a share code
+pub(crate) fn encode(expr: &SExpr, bindings: &[(String, f64)]) -> String {
+    let mut bytes = vec![SHARE_VERSION];
+    write_expr(&mut bytes, expr);
+    write_u32(&mut bytes, bindings.len());
+    for (name, value) in bindings {
+        write_str(&mut bytes, name);
+        bytes.extend(value.to_le_bytes());
+    }
+    format!("{SHARE_PREFIX}{}", base64_encode(&bytes))
+}
+
+/// Decode a share code produced by [`encode`]
+pub(crate) fn decode(code: &str) -> Result<Shared> {
+    let Some(encoded) = code.trim().strip_prefix(SHARE_PREFIX) else {
+        return Err(anyhow!(
+            "Invalid share code, expected it to start with {SHARE_PREFIX}"
+        ));
+    };
+    let bytes = base64_decode(encoded)?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        version: SHARE_VERSION,
+    };
+    let version = reader.byte()?;
+    if version > SHARE_VERSION {
+        return Err(anyhow!(
+            "Unsupported share code version {version}, it comes from a newer release which writes up to version {SHARE_VERSION}"
+        ));
+    }
+    if version < OLDEST_SHARE_VERSION {
+        return Err(anyhow!(
+            "Unsupported share code version {version}, expected {OLDEST_SHARE_VERSION} to {SHARE_VERSION}"
+        ));
+    }
+    reader.version = version;
+    let expr = reader.expr()?;
+    let mut bindings = Vec::new();
+    for _ in 0..reader.u32()? {
+        let name = reader.string()?;
+        bindings.push((name, f64::from_le_bytes(reader.array()?)));
+    }
+    if !reader.bytes.is_empty() {
+        return Err(anyhow!("Invalid share code, unexpected data after the end"));
+    }
+    Ok(Shared { expr, bindings })
+}
+
+/// Append an expression to the encoded bytes
+fn write_expr(bytes: &mut Vec<u8>, expr: &SExpr) {
+    match expr {
+        SExpr::Atom(atom) => write_atom(bytes, atom),
+        SExpr::Cons(op, operands) => {
+            bytes.push(TAG_CONS);
+            write_atom(bytes, op);
+            write_u32(bytes, operands.len());
+            for operand in operands {
+                write_expr(bytes, operand);
+            }
+        }
+        SExpr::Where(body, bindings) => {
+            bytes.push(TAG_WHERE);
+            write_expr(bytes, body);
+            write_u32(bytes, bindings.len());
+            for (name, value) in bindings {
+                write_str(bytes, name);
+                write_expr(bytes, value);
+            }
+        }
+    }
+}
+
+/// Append an atom to the encoded bytes
+fn write_atom(bytes: &mut Vec<u8>, atom: &SExprAtom) {
+    match atom {
+        SExprAtom::Number(num) => {
+            bytes.push(TAG_NUMBER);
+            bytes.extend(num.to_le_bytes());
+        }
+        SExprAtom::Integer(num) => {
+            bytes.push(TAG_INTEGER);
+            bytes.extend(num.to_le_bytes());
+        }
+        SExprAtom::Variable(name) => {
+            bytes.push(TAG_VARIABLE);
+            write_str(bytes, name);
+        }
+        SExprAtom::Op(op) => {
+            bytes.push(TAG_OP);
+            write_str(bytes, op.symbol());
+        }
+    }
+}
+
+/// Append a length or count to the encoded bytes
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    // Expressions typed at a prompt are nowhere near 4GB
+    bytes.extend((value as u32).to_le_bytes());
+}
+
+/// Append a string to the encoded bytes
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    write_u32(bytes, value.len());
+    bytes.extend(value.as_bytes());
+}
+
+/// Reads the parts of a decoded share code in order
+struct Reader<'a> {
+    /// The bytes which haven't been read yet
+    bytes: &'a [u8],
+    /// Version of the binary layout being read
+    version: u8,
+}
+
+impl Reader<'_> {
+    /// Read the next N bytes
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let Some((head, rest)) = self.bytes.split_first_chunk::<N>() else {
+            return Err(anyhow!("Invalid share code, it ends too early"));
+        };
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    /// Read a single byte
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Read a length or count
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    /// Read a string
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        if len > self.bytes.len() {
+            return Err(anyhow!("Invalid share code, it ends too early"));
+        }
+        let (text, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        String::from_utf8(text.to_vec())
+            .map_err(|_| anyhow!("Invalid share code, a name isn't valid UTF-8"))
+    }
+
+    /// Read an expression
+    fn expr(&mut self) -> Result<SExpr> {
+        match self.byte()? {
+            TAG_CONS => {
+                let op = self.atom()?;
+                let count = self.u32()?;
+                let operands = (0..count)
+                    .map(|_| self.expr())
+                    .collect::<Result<Vec<SExpr>>>()?;
+                Ok(SExpr::Cons(op, operands))
+            }
+            TAG_WHERE => {
+                let body = self.expr()?;
+                let count = self.u32()?;
+                let bindings = (0..count)
+                    .map(|_| Ok((self.string()?, self.expr()?)))
+                    .collect::<Result<Vec<(String, SExpr)>>>()?;
+                Ok(SExpr::Where(Box::new(body), bindings))
+            }
+            tag => Ok(SExpr::Atom(self.atom_with_tag(tag)?)),
+        }
+    }
+
+    /// Read an atom
+    fn atom(&mut self) -> Result<SExprAtom> {
+        let tag = self.byte()?;
+        self.atom_with_tag(tag)
+    }
+
+    /// Read the rest of an atom, after its tag
+    fn atom_with_tag(&mut self, tag: u8) -> Result<SExprAtom> {
+        match tag {
+            TAG_NUMBER => Ok(SExprAtom::Number(f64::from_le_bytes(self.array()?))),
+            TAG_INTEGER => Ok(SExprAtom::Integer(i64::from_le_bytes(self.array()?))),
+            TAG_VARIABLE => Ok(SExprAtom::Variable(self.string()?)),
+            // Version 1 wrote operators as a single character
+            TAG_OP if self.version == 1 => char::from_u32(self.u32()?)
+                .and_then(|symbol| Operator::from_symbol(&symbol.to_string()))
+                .map(SExprAtom::Op)
+                .ok_or(anyhow!("Invalid share code, unknown operator")),
+            TAG_OP => {
+                let symbol = self.string()?;
+                Operator::from_symbol(&symbol)
+                    .map(SExprAtom::Op)
+                    .ok_or(anyhow!("Invalid share code, unknown operator {symbol}"))
+            }
+            tag => Err(anyhow!("Invalid share code, unknown node tag {tag}")),
+        }
+    }
+}
+
+/// Encode bytes as URL safe base64, without padding
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        // Three bytes make four characters, fewer bytes make one more
+        // character than bytes
+        for i in 0..=chunk.len() {
+            let index = (group >> (18 - 6 * i)) & 0x3f;
+            encoded.push(BASE64_ALPHABET[index as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decode URL safe base64, without padding
+fn base64_decode(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let values = text
+        .chars()
+        .map(|c| {
+            BASE64_ALPHABET
+                .iter()
+                .position(|a| *a as char == c)
+                .ok_or(anyhow!("Invalid share code, unexpected character {c}"))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(anyhow!("Invalid share code, it ends too early"));
+        }
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, value)| {
+            group | (*value as u32) << (18 - 6 * i)
+        });
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test_share {
+    use super::*;
+    use pratt_calculator::PrattParser;
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let expr = PrattParser::parse("-⌊rate * 1.5⌋ + u^2 + 3! where u = x % 7")?;
+        let bindings = vec![("rate".to_string(), 0.25), ("x".to_string(), -12.0)];
+        let code = encode(&expr, &bindings);
+        assert!(code.starts_with("pc1:"));
+        let shared = decode(&code)?;
+        assert_eq!(shared.expr.to_string(), expr.to_string());
+        assert_eq!(shared.bindings, bindings);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_version_1() -> Result<()> {
+        // x * 2, with x = 1.5, writing the operator as a u32 character
+        let mut bytes = vec![1, TAG_CONS, TAG_OP];
+        bytes.extend(u32::from('*').to_le_bytes());
+        write_u32(&mut bytes, 2);
+        bytes.push(TAG_VARIABLE);
+        write_str(&mut bytes, "x");
+        bytes.push(TAG_INTEGER);
+        bytes.extend(2i64.to_le_bytes());
+        write_u32(&mut bytes, 1);
+        write_str(&mut bytes, "x");
+        bytes.extend(1.5f64.to_le_bytes());
+        let shared = decode(&format!("{SHARE_PREFIX}{}", base64_encode(&bytes)))?;
+        assert_eq!(shared.expr.to_string(), "(* x 2)");
+        assert_eq!(shared.bindings, vec![("x".to_string(), 1.5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64() -> Result<()> {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"\xfb\xff", "-_8"),
+        ] {
+            assert_eq!(base64_encode(bytes), text);
+            assert_eq!(base64_decode(text)?, bytes);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_codes() -> Result<()> {
+        let code = encode(&PrattParser::parse("x + 1")?, &[("x".to_string(), 2.0)]);
+        let mut trailing = base64_decode(&code[SHARE_PREFIX.len()..])?;
+        trailing.push(0);
+        let cases = [
+            ("x + 1".to_string(), "expected it to start with pc1:"),
+            (code[..code.len() - 3].to_string(), "it ends too early"),
+            (
+                format!("{SHARE_PREFIX}{}", base64_encode(&trailing)),
+                "unexpected data after the end",
+            ),
+            (code.replacen('A', "!", 1), "unexpected character !"),
+            (
+                format!("{SHARE_PREFIX}Aw"),
+                "Unsupported share code version 3, it comes from a newer release",
+            ),
+            (
+                format!("{SHARE_PREFIX}AA"),
+                "Unsupported share code version 0, expected 1 to 2",
+            ),
+            (format!("{SHARE_PREFIX}Agk"), "unknown node tag 9"),
+        ];
+        for (code, expected) in cases {
+            let err = decode(&code).unwrap_err();
+            assert!(
+                err.to_string().contains(expected),
+                "decoding {code} gave {err}, expected {expected}"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! Evaluating an expression while sweeping one variable over a range
+// Standard Library Uses
+
+// External Uses
+use anyhow::{Context, Result, anyhow};
+
+// Local Uses
+use pratt_calculator::interpreter::interpreter::Interpreter;
+
+/// Largest number of points a single sweep will evaluate
+pub(crate) const MAX_SWEEP_POINTS: usize = 10_000;
+
+/// The results of evaluating an expression at each point of a sweep
+#[derive(Clone, Debug)]
+pub(crate) struct Sweep {
+    /// The expression which was evaluated
+    pub(crate) expr: String,
+    /// The variable which was swept
+    pub(crate) variable: String,
+    /// Each value of the variable along with the result there, non-finite
+    /// results are kept rather than stopping the sweep
+    pub(crate) rows: Vec<(f64, f64)>,
+}
+
+impl Sweep {
+    /// Number of results which are NaN or infinite
+    pub(crate) fn non_finite_count(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|(_, value)| !value.is_finite())
+            .count()
+    }
+
+    /// Render the sweep as a table with aligned columns, writing values
+    /// with format and marking non-finite results with brackets
+    pub(crate) fn render(&self, format: impl Fn(f64) -> String) -> String {
+        let cells = self
+            .rows
+            .iter()
+            .map(|&(point, value)| {
+                let value = match value.is_finite() {
+                    true => format(value),
+                    false => format!("[{}]", format(value)),
+                };
+                (format(point), value)
+            })
+            .collect::<Vec<_>>();
+        let point_width = cells
+            .iter()
+            .map(|(point, _)| point.chars().count())
+            .chain([self.variable.chars().count()])
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![format!("{:>point_width$} | {}", self.variable, self.expr)];
+        lines.extend(
+            cells
+                .iter()
+                .map(|(point, value)| format!("{point:>point_width$} | {value}")),
+        );
+        match self.non_finite_count() {
+            0 => {}
+            1 => lines.push("1 result was not finite".to_string()),
+            n => lines.push(format!("{n} results were not finite")),
+        }
+        lines.join("\n")
+    }
+}
+
+/// Evaluate expr with variable set to each of lo, lo + step, ... up to
+/// hi (inclusive, allowing for rounding in the step)
+///
+/// The variable is only bound while evaluating, so any existing value is
+/// left as it was.
+pub(crate) fn sweep(
+    interpreter: &mut Interpreter,
+    expr: &str,
+    variable: &str,
+    lo: f64,
+    hi: f64,
+    step: f64,
+) -> Result<Sweep> {
+    if !lo.is_finite() || !hi.is_finite() {
+        return Err(anyhow!("Can't sweep over a non-finite range {lo} to {hi}"));
+    }
+    if !(step > 0f64 && step.is_finite()) {
+        return Err(anyhow!("The step of a sweep must be positive, got {step}"));
+    }
+    if hi < lo {
+        return Err(anyhow!(
+            "The end of a sweep must not be before its start, got {lo} to {hi}"
+        ));
+    }
+    // Points are worked out from their index rather than by repeatedly
+    // adding the step, so rounding errors don't accumulate
+    let steps = ((hi - lo) / step + 1e-9).floor();
+    if steps >= MAX_SWEEP_POINTS as f64 {
+        return Err(anyhow!(
+            "A sweep can have at most {MAX_SWEEP_POINTS} points, this one has {}",
+            steps + 1f64
+        ));
+    }
+    let rows = (0..=steps as usize)
+        .map(|i| {
+            let point = lo + step * i as f64;
+            interpreter
+                .interpret_with(expr, &[(variable, point)])
+                .with_context(|| format!("Failed to evaluate {expr} at {variable} = {point}"))
+                .map(|value| (point, value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Sweep {
+        expr: expr.to_string(),
+        variable: variable.to_string(),
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod test_sweep {
+    use super::*;
+
+    #[test]
+    fn test_range_sweep() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        test_interpreter.interpret("t = 7")?;
+        let results = sweep(&mut test_interpreter, "t^2", "t", 0f64, 1f64, 0.25)?;
+        assert_eq!(
+            results.rows,
+            vec![
+                (0f64, 0f64),
+                (0.25, 0.0625),
+                (0.5, 0.25),
+                (0.75, 0.5625),
+                (1f64, 1f64)
+            ]
+        );
+        // The swept variable is scoped to the sweep
+        assert_eq!(test_interpreter.interpret("t")?, 7f64);
+        // Rounding in the step doesn't drop the last point
+        let results = sweep(&mut test_interpreter, "x", "x", 0f64, 1f64, 0.1)?;
+        assert_eq!(results.rows.len(), 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_finite_results() -> Result<()> {
+        let mut test_interpreter = Interpreter::new();
+        let results = sweep(&mut test_interpreter, "1 / x", "x", -1f64, 1f64, 1f64)?;
+        assert_eq!(results.non_finite_count(), 1);
+        assert_eq!(
+            results.render(|value| value.to_string()),
+            " x | 1 / x\n-1 | -1\n 0 | [inf]\n 1 | 1\n1 result was not finite"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_errors() {
+        let mut test_interpreter = Interpreter::new();
+        let cases = [
+            (
+                0f64,
+                1f64,
+                0f64,
+                "The step of a sweep must be positive, got 0",
+            ),
+            (
+                1f64,
+                0f64,
+                1f64,
+                "The end of a sweep must not be before its start, got 1 to 0",
+            ),
+            (
+                0f64,
+                1f64,
+                1e-6,
+                "A sweep can have at most 10000 points, this one has 1000001",
+            ),
+        ];
+        for (lo, hi, step, expected) in cases {
+            let err = sweep(&mut test_interpreter, "x", "x", lo, hi, step).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+        let err = sweep(&mut test_interpreter, "x + y", "x", 0f64, 1f64, 1f64).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to evaluate x + y at x = 0");
+    }
+}
@@ -0,0 +1,169 @@
+//! Conversion between units of measurement
+// Standard Library Uses
+use std::fmt;
+
+// External Uses
+use anyhow::{Result, anyhow};
+
+/// The physical dimension measured by a unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dimension::Length => write!(f, "length"),
+            Dimension::Mass => write!(f, "mass"),
+            Dimension::Time => write!(f, "time"),
+        }
+    }
+}
+
+/// A unit which converts linearly to the base unit of its dimension
+struct Unit {
+    /// The abbreviation used to refer to the unit
+    name: &'static str,
+    /// The dimension the unit measures
+    dimension: Dimension,
+    /// How many base units (meters, kilograms or seconds) one of this
+    /// unit is
+    factor: f64,
+}
+
+impl Unit {
+    /// Create a new unit
+    const fn new(name: &'static str, dimension: Dimension, factor: f64) -> Self {
+        Unit {
+            name,
+            dimension,
+            factor,
+        }
+    }
+}
+
+/// Table of all the known units
+const UNITS: &[Unit] = &[
+    // Length, relative to meters
+    Unit::new("mm", Dimension::Length, 0.001),
+    Unit::new("cm", Dimension::Length, 0.01),
+    Unit::new("m", Dimension::Length, 1.0),
+    Unit::new("km", Dimension::Length, 1000.0),
+    Unit::new("in", Dimension::Length, 0.0254),
+    Unit::new("ft", Dimension::Length, 0.3048),
+    Unit::new("yd", Dimension::Length, 0.9144),
+    Unit::new("mi", Dimension::Length, 1609.344),
+    Unit::new("nmi", Dimension::Length, 1852.0),
+    // Mass, relative to kilograms
+    Unit::new("mg", Dimension::Mass, 0.000001),
+    Unit::new("g", Dimension::Mass, 0.001),
+    Unit::new("kg", Dimension::Mass, 1.0),
+    Unit::new("t", Dimension::Mass, 1000.0),
+    Unit::new("oz", Dimension::Mass, 0.028349523125),
+    Unit::new("lb", Dimension::Mass, 0.45359237),
+    Unit::new("st", Dimension::Mass, 6.35029318),
+    // Time, relative to seconds
+    Unit::new("s", Dimension::Time, 1.0),
+    Unit::new("min", Dimension::Time, 60.0),
+    Unit::new("h", Dimension::Time, 3600.0),
+    Unit::new("d", Dimension::Time, 86400.0),
+];
+
+/// Look up a unit by its name
+fn find_unit(name: &str) -> Result<&'static Unit> {
+    UNITS
+        .iter()
+        .find(|unit| unit.name == name)
+        .ok_or(anyhow!("Unknown unit {name}"))
+}
+
+/// Convert value from one unit into another
+pub(crate) fn convert(value: f64, from: &str, to: &str) -> Result<f64> {
+    let from_unit = find_unit(from)?;
+    let to_unit = find_unit(to)?;
+    if from_unit.dimension != to_unit.dimension {
+        return Err(anyhow!(
+            "Cannot convert {from} ({}) to {to} ({})",
+            from_unit.dimension,
+            to_unit.dimension
+        ));
+    }
+    Ok(value * from_unit.factor / to_unit.factor)
+}
+
+/// Parse a time written as `H:M:S` into a number of seconds, returning
+/// None if text isn't written in that format at all
+///
+/// Hours can be any whole number, minutes and seconds must be below 60,
+/// and the seconds may have a fractional part.
+pub(crate) fn parse_time(text: &str) -> Option<Result<f64>> {
+    let fields = text.split(':').collect::<Vec<&str>>();
+    let [hours, minutes, seconds] = fields[..] else {
+        return None;
+    };
+    Some(
+        parse_time_fields(hours, minutes, seconds)
+            .map_err(|err| anyhow!("Invalid time {text}, {err}")),
+    )
+}
+
+/// Combine the fields of an `H:M:S` time into a number of seconds
+fn parse_time_fields(hours: &str, minutes: &str, seconds: &str) -> Result<f64> {
+    let is_whole = |field: &str| !field.is_empty() && field.chars().all(|c| c.is_ascii_digit());
+    if !is_whole(hours) || !is_whole(minutes) {
+        return Err(anyhow!("hours and minutes must be whole numbers"));
+    }
+    let (whole_seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, "0"));
+    if !is_whole(whole_seconds) || !is_whole(fraction) {
+        return Err(anyhow!("seconds must be a number"));
+    }
+    let hours = hours.parse::<f64>()?;
+    let minutes = minutes.parse::<f64>()?;
+    let seconds = seconds.parse::<f64>()?;
+    if minutes >= 60f64 || seconds >= 60f64 {
+        return Err(anyhow!("minutes and seconds must be less than 60"));
+    }
+    Ok(hours * 3600f64 + minutes * 60f64 + seconds)
+}
+
+#[cfg(test)]
+mod test_units {
+    use super::*;
+
+    #[test]
+    fn test_convert() -> Result<()> {
+        assert_eq!(convert(1f64, "km", "m")?, 1000f64);
+        assert!((convert(100f64, "km", "mi")? - 62.137119).abs() < 1e-6);
+        assert!((convert(1f64, "lb", "g")? - 453.59237).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_errors() -> Result<()> {
+        let err = convert(1f64, "km", "parsec").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown unit parsec");
+        let err = convert(1f64, "km", "kg").unwrap_err();
+        assert_eq!(err.to_string(), "Cannot convert km (length) to kg (mass)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_time() -> Result<()> {
+        assert_eq!(parse_time("1:30:00").transpose()?, Some(5400f64));
+        assert_eq!(parse_time("0:00:12.5").transpose()?, Some(12.5));
+        assert_eq!(parse_time("100:01:01").transpose()?, Some(360061f64));
+        assert!(parse_time("1:30").is_none());
+        assert!(parse_time("90").is_none());
+        let err = parse_time("1:75:00").unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid time 1:75:00, minutes and seconds must be less than 60"
+        );
+        assert!(parse_time("1:-3:00").unwrap().is_err());
+        assert!(parse_time("1:30:").unwrap().is_err());
+        Ok(())
+    }
+}
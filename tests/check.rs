@@ -0,0 +1,41 @@
+//! Integration tests for the --check parse only mode
+use std::process::Command;
+
+/// Run the calculator binary with the given arguments, returning whether
+/// it succeeded along with its stdout and stderr
+fn run(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pratt_calculator"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to run the calculator binary");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_check_file() {
+    let (success, stdout, stderr) = run(&["--check", "--file", "tests/data/check.calc"]);
+    assert!(!success);
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("line 3: Failed to parse x +"));
+    assert!(!stderr.contains("line 1"));
+    assert!(stderr.contains("1 of 2 expressions failed to parse"));
+}
+
+#[test]
+fn test_check_arguments() {
+    // Only parsed, so the undefined variable isn't an error
+    let (success, stdout, _) = run(&["--check", "y", "*", "2"]);
+    assert!(success);
+    assert!(stdout.is_empty());
+    let (success, _, stderr) = run(&["--check", "(y * 2"]);
+    assert!(!success);
+    assert!(stderr.contains("1 unclosed parenthesis"));
+    let (success, _, stderr) = run(&["--check", "--file", "tests/data/missing.calc"]);
+    assert!(!success);
+    assert!(stderr.contains("Failed to read tests/data/missing.calc"));
+}
@@ -0,0 +1,135 @@
+//! Runs the golden-value corpus in tests/corpus
+//!
+//! Each `.calc` file holds lines of `expression => expected output`,
+//! or `expression => error: <part of the message>` for inputs which
+//! should fail. Blank lines and lines starting with `#` are ignored.
+//! Every file is piped through a fresh run of the REPL, so assignments
+//! carry over between the lines of a file but not between files. Running
+//! the tests with `UPDATE_CORPUS=1` rewrites any failing expectations
+//! with the current output.
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow};
+use pratt_calculator::fsutil;
+
+/// Prompt given to the REPL, so each output can be picked out of stdout
+const CORPUS_PROMPT: &str = "<corpus>";
+
+/// Run the lines of input through the calculator's REPL, returning the
+/// output of each
+fn run_repl(inputs: &[&str]) -> Result<Vec<String>> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pratt_calculator"))
+        // Without line editing the REPL reads plain lines from stdin
+        .env("TERM", "dumb")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run the calculator binary")?;
+    let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+    writeln!(stdin, ":prompt {CORPUS_PROMPT}")?;
+    for input in inputs {
+        writeln!(stdin, "{input}")?;
+    }
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Each input's output follows its prompt, and the last prompt is
+    // answered by the REPL quitting
+    let outputs = stdout.split(CORPUS_PROMPT).collect::<Vec<&str>>();
+    let Some(start) = outputs.len().checked_sub(inputs.len() + 1) else {
+        return Err(anyhow!("Expected an output for every input, got {stdout}"));
+    };
+    Ok(outputs[start..outputs.len() - 1]
+        .iter()
+        .map(|output| output.trim_end_matches('\n').to_string())
+        .collect())
+}
+
+/// Split REPL output into either a value or an error message
+fn corpus_output(output: &str) -> String {
+    match output.split_once("Error: ") {
+        Some((_, message)) => format!("error: {message}"),
+        None => output.to_string(),
+    }
+}
+
+/// Check whether the output of the REPL matches a corpus expectation
+fn corpus_matches(expected: &str, actual: &str) -> bool {
+    match (
+        expected.strip_prefix("error:"),
+        actual.strip_prefix("error:"),
+    ) {
+        (Some(expected_message), Some(actual_message)) => {
+            actual_message.contains(expected_message.trim())
+        }
+        _ => expected == actual,
+    }
+}
+
+#[test]
+fn test_golden_corpus() -> Result<()> {
+    let corpus_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("corpus");
+    let update = std::env::var("UPDATE_CORPUS").is_ok_and(|val| val == "1");
+    let mut paths = fs::read_dir(&corpus_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "calc"));
+    paths.sort();
+    if paths.is_empty() {
+        return Err(anyhow!("No corpus files found in {}", corpus_dir.display()));
+    }
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let contents = fs::read_to_string(&path)?;
+        let mut entries = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let Some((input, expected)) = entry.rsplit_once("=>") else {
+                return Err(anyhow!(
+                    "{}:{} is missing `=>`",
+                    path.display(),
+                    line_number + 1
+                ));
+            };
+            entries.push((line_number, input.trim(), expected.trim()));
+        }
+        let inputs = entries
+            .iter()
+            .map(|(_, input, _)| *input)
+            .collect::<Vec<&str>>();
+        let outputs = run_repl(&inputs)?;
+
+        let mut updated_lines = contents
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        for ((line_number, input, expected), output) in entries.iter().zip(outputs) {
+            let actual = corpus_output(&output);
+            if !corpus_matches(expected, &actual) {
+                failures.push(format!(
+                    "{}:{}: {input} gave {actual}, expected {expected}",
+                    path.display(),
+                    line_number + 1
+                ));
+                updated_lines[*line_number] = format!("{input} => {actual}");
+            }
+        }
+        if update {
+            fsutil::write_atomic(&path, (updated_lines.join("\n") + "\n").as_bytes())?;
+        }
+    }
+
+    if !failures.is_empty() && !update {
+        return Err(anyhow!("Corpus failures:\n{}", failures.join("\n")));
+    }
+    Ok(())
+}
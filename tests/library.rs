@@ -0,0 +1,40 @@
+//! Integration tests using the calculator as a library dependency
+use anyhow::Result;
+use pratt_calculator::{Interpreter, PrattParser};
+
+#[test]
+fn test_interpret() -> Result<()> {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.interpret("2+2")?, 4.0);
+    interpreter.interpret("rate = 0.5")?;
+    assert_eq!(interpreter.interpret("rate * 10")?, 5.0);
+    assert!(interpreter.interpret("rate +").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse() -> Result<()> {
+    let parsed = PrattParser::parse("1 + 2 * x")?;
+    assert_eq!(parsed.to_string(), "(+ 1 (* 2 x))");
+    Ok(())
+}
+
+#[test]
+fn test_prelude() -> Result<()> {
+    use pratt_calculator::prelude::*;
+
+    let mut interpreter = Interpreter::builder()
+        .preset(Preset::Interactive)
+        .division_mode(DivisionMode::Integer)
+        .build();
+    assert_eq!(interpreter.interpret("7 / 2")?, 3.0);
+    // The option enums are non-exhaustive, so matching them needs a
+    // wildcard arm
+    let name = match interpreter.division_mode() {
+        DivisionMode::Float => "float",
+        DivisionMode::Integer => "int",
+        _ => "other",
+    };
+    assert_eq!(name, "int");
+    Ok(())
+}
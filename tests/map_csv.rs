@@ -0,0 +1,44 @@
+//! Integration tests for the --map-csv batch mode
+use std::process::Command;
+
+/// Run the calculator binary with the given arguments, returning whether
+/// it succeeded along with its stdout and stderr
+fn run(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pratt_calculator"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("Failed to run the calculator binary");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_map_csv() {
+    let (success, stdout, _) = run(&["--map-csv", "tests/data/parts.csv", "price * qty"]);
+    assert!(success);
+    assert_eq!(stdout, "6\n2.5\n3\n");
+    // The expression may be split over several arguments
+    let (success, stdout, _) = run(&["--map-csv", "tests/data/parts.csv", "qty", "+", "1"]);
+    assert!(success);
+    assert_eq!(stdout, "5\n11\n31\n");
+}
+
+#[test]
+fn test_map_csv_errors() {
+    let (success, _, stderr) = run(&["--map-csv", "tests/data/parts.csv", "price * weight"]);
+    assert!(!success);
+    assert!(stderr.contains("Unknown column weight, the columns are item, price, qty"));
+    let (success, _, stderr) = run(&["--map-csv", "tests/data/parts.csv", "item"]);
+    assert!(!success);
+    assert!(stderr.contains("Non-numeric value \"nut\" in column item on line 2"));
+    let (success, _, stderr) = run(&["--map-csv", "tests/data/missing.csv", "1"]);
+    assert!(!success);
+    assert!(stderr.contains("Failed to read tests/data/missing.csv"));
+    let (success, _, stderr) = run(&["--map-csv", "tests/data/parts.csv"]);
+    assert!(!success);
+    assert!(stderr.contains("Expected --map-csv <file> <expression>"));
+}